@@ -0,0 +1,390 @@
+use neat_gru::train::Train;
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use wasm_flappy_bird::champions::Champions;
+use wasm_flappy_bird::chart;
+use wasm_flappy_bird::curriculum::Schedule;
+use wasm_flappy_bird::game::draw::Hud;
+use wasm_flappy_bird::game::fitness::Fitness;
+use wasm_flappy_bird::game::mode::GameMode;
+use wasm_flappy_bird::game::replay::Replay;
+use wasm_flappy_bird::game::sensors::Sensors;
+use wasm_flappy_bird::game::simulation::Simulation;
+use wasm_flappy_bird::game::svg;
+use wasm_flappy_bird::statistics::{Aggregation, Statistics};
+use wasm_flappy_bird::training_simulation::TrainingSimulation;
+use wasm_flappy_bird::{configure_training, GameParams};
+
+const USAGE: &str = "Usage: flappy-train [OPTIONS]
+
+Options:
+    --game-type <0|1>       0 for classic, 1 for aerodynamics (default: 0)
+    --birds <N>             Number of birds per generation (default: 500)
+    --hole-size <N>         Size of the hole between pipes (default: 200)
+    --iterations <N>        Number of generations to run (default: 5000)
+    --delta-threshold <X>   Distance above which topologies are different species (default: 2)
+    --formula <C1,C2,C3>    Weights of the compatibility distance (default: 0.8,0.8,0.3)
+    --max-layers <N>        Maximum number of layers of a network (default: 10)
+    --max-per-layers <N>    Maximum number of neurons per layer (default: 10)
+    --width <N>             Width of the world (default: 700)
+    --height <N>            Height of the world (default: 800)
+    --sensors <S1,S2,...>   Inputs of the networks (default: pipe_x,hole_offset,velocity)
+                            among pipe_x, hole_offset, velocity, second_pipe_x,
+                            second_hole_offset, gap_top, gap_bottom, ceiling, floor,
+                            pipe_speed and ticks_since_flap
+    --fitness <NAME>        Score of the birds among ticks, pipes_passed, pipe_bonus and
+                            gap_centre (default: ticks)
+    --curriculum <NAME>     Shrink the hole down to --hole-size during the training, among
+                            fixed, linear, step and performance (default: fixed)
+    --curriculum-start <N>  Hole size of the first generation of a curriculum (default: 300)
+    --curriculum-generations <N>
+                            Generations to reach --hole-size for linear, between two shrinks
+                            for step (default: 50)
+    --curriculum-step <N>   Pixels removed at every shrink of step and performance (default: 10)
+    --curriculum-threshold <X>
+                            Pipes to pass for performance to shrink the hole (default: 5)
+    --pipe-amplitude <X>    Make the gaps move up and down by X, 0 keeps them still (default: 0)
+    --pipe-frequency <X>    Oscillations of the gaps per 100 ticks (default: 0.5)
+    --episodes <N>          Courses every generation is played on (default: 1)
+    --aggregation <NAME>    How the scores of the episodes are combined, among mean, min and
+                            median (default: mean)
+    --max-ticks <N>         End a generation after N ticks, 0 for no limit (default: 0)
+    --max-pipes <N>         End a generation after N pipes, 0 for no limit (default: 0)
+    --gravity <X>           Velocity the birds lose every tick (default: 0.5)
+    --jump-impulse <X>      Velocity after a jump in classic, added by a jump in aerodynamics
+                            (default: 10 in classic, 1 in aerodynamics)
+    --pipe-speed <X>        Pixels the pipes move every tick (default: 4)
+    --pipe-spacing <X>      Distance between two pipes (default: 500)
+    --speed-increase <X>    Speed gained at most by the pipes with --speed (default: 2.5)
+    --speed-increase-rate <X>
+                            How quickly the pipes accelerate with --speed (default: 0.002)
+    --seed <N>              Seed of the courses, random if not given
+    --speed                 Make the pipes accelerate over time
+    --replay-dir <DIR>      Save the replay of every generation in DIR
+    --stats <FILE>          Write the statistics of every generation to FILE, as CSV if it
+                            ends with .csv and as JSON lines otherwise
+    --chart <FILE>          Save a chart of the fitness and species of every generation as SVG
+                            in FILE at the end of the training
+    --play-replay <FILE>    Play a replay back instead of training and compare the scores
+    --save-champions <FILE> Save the best networks in FILE at the end of the training
+    --play-champions <FILE> Play the saved champions instead of training and print their scores
+    --snapshot <FILE>       Save a frame of --play-replay or --play-champions as SVG in FILE
+    --snapshot-tick <N>     Tick of the frame saved by --snapshot (default: 0)
+    -h, --help              Print this message";
+
+struct Args {
+    params: GameParams,
+    replay_dir: Option<PathBuf>,
+    stats: Option<PathBuf>,
+    chart: Option<PathBuf>,
+    play_replay: Option<PathBuf>,
+    save_champions: Option<PathBuf>,
+    play_champions: Option<PathBuf>,
+    snapshot: Option<PathBuf>,
+    snapshot_tick: usize,
+}
+
+/// Parses the value following a flag
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("Missing value for {}", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut params = GameParams::new(0, 500, 0, 200);
+    let mut replay_dir = None;
+    let mut stats = None;
+    let mut chart = None;
+    let mut play_replay = None;
+    let mut save_champions = None;
+    let mut play_champions = None;
+    let mut snapshot = None;
+    let mut snapshot_tick = 0;
+    // Applied over the preset of the game type once it is known
+    let mut physics_flags: Vec<(String, f64)> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match &*flag {
+            "--game-type" => params.game_type = parse_value(&flag, args.next())?,
+            "--birds" => params.birds_count = parse_value(&flag, args.next())?,
+            "--hole-size" => params.hole_size = parse_value(&flag, args.next())?,
+            "--iterations" => params.iterations = parse_value(&flag, args.next())?,
+            "--delta-threshold" => params.delta_threshold = parse_value(&flag, args.next())?,
+            "--formula" => {
+                let formula: String = parse_value(&flag, args.next())?;
+                let c: Vec<f64> = formula
+                    .split(',')
+                    .map(|c| parse_value(&flag, Some(String::from(c))))
+                    .collect::<Result<_, _>>()?;
+                if c.len() != 3 {
+                    return Err(format!("Invalid value for {}: {}", flag, formula));
+                }
+                params.c1 = c[0];
+                params.c2 = c[1];
+                params.c3 = c[2];
+            }
+            "--max-layers" => params.max_layers = parse_value(&flag, args.next())?,
+            "--max-per-layers" => params.max_per_layers = parse_value(&flag, args.next())?,
+            "--width" => params.width = parse_value(&flag, args.next())?,
+            "--height" => params.height = parse_value(&flag, args.next())?,
+            "--sensors" => {
+                let names: String = parse_value(&flag, args.next())?;
+                params.sensors = Sensors::from_names(&names)?.mask();
+            }
+            "--fitness" => {
+                let name: String = parse_value(&flag, args.next())?;
+                params.fitness = Fitness::from_name(&name)?.id();
+            }
+            "--curriculum" => {
+                let name: String = parse_value(&flag, args.next())?;
+                params.curriculum = Schedule::from_name(&name)?.id();
+            }
+            "--curriculum-start" => params.curriculum_start = parse_value(&flag, args.next())?,
+            "--curriculum-generations" => {
+                params.curriculum_generations = parse_value(&flag, args.next())?
+            }
+            "--curriculum-step" => params.curriculum_step = parse_value(&flag, args.next())?,
+            "--curriculum-threshold" => {
+                params.curriculum_threshold = parse_value(&flag, args.next())?
+            }
+            "--pipe-amplitude" => params.pipe_amplitude = parse_value(&flag, args.next())?,
+            "--pipe-frequency" => params.pipe_frequency = parse_value(&flag, args.next())?,
+            "--episodes" => params.episodes = parse_value(&flag, args.next())?,
+            "--aggregation" => {
+                let name: String = parse_value(&flag, args.next())?;
+                params.aggregation = Aggregation::from_name(&name)?.id();
+            }
+            "--max-ticks" => params.max_ticks = parse_value(&flag, args.next())?,
+            "--max-pipes" => params.max_pipes = parse_value(&flag, args.next())?,
+            "--gravity"
+            | "--jump-impulse"
+            | "--pipe-speed"
+            | "--pipe-spacing"
+            | "--speed-increase"
+            | "--speed-increase-rate" => {
+                let value = parse_value(&flag, args.next())?;
+                physics_flags.push((flag, value));
+            }
+            "--seed" => params.seed = parse_value(&flag, args.next())?,
+            "--speed" => params.speed = true,
+            "--replay-dir" => replay_dir = Some(parse_value(&flag, args.next())?),
+            "--stats" => stats = Some(parse_value(&flag, args.next())?),
+            "--chart" => chart = Some(parse_value(&flag, args.next())?),
+            "--play-replay" => play_replay = Some(parse_value(&flag, args.next())?),
+            "--save-champions" => save_champions = Some(parse_value(&flag, args.next())?),
+            "--play-champions" => play_champions = Some(parse_value(&flag, args.next())?),
+            "--snapshot" => snapshot = Some(parse_value(&flag, args.next())?),
+            "--snapshot-tick" => snapshot_tick = parse_value(&flag, args.next())?,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("Unknown argument: {}", flag)),
+        }
+    }
+
+    let mut physics = GameMode::try_from(params.game_type)?.physics();
+    for (flag, value) in physics_flags {
+        let field = match &*flag {
+            "--gravity" => &mut physics.gravity,
+            "--jump-impulse" => &mut physics.jump_impulse,
+            "--pipe-speed" => &mut physics.pipe_speed,
+            "--pipe-spacing" => &mut physics.pipe_spacing,
+            "--speed-increase" => &mut physics.speed_increase,
+            _ => &mut physics.speed_increase_rate,
+        };
+        *field = value;
+    }
+    params.set_physics(physics);
+    params.validate()?;
+
+    Ok(Args {
+        params,
+        replay_dir,
+        stats,
+        chart,
+        play_replay,
+        save_champions,
+        play_champions,
+        snapshot,
+        snapshot_tick,
+    })
+}
+
+fn save_snapshot(simulation: &Simulation, path: &Path) -> Result<(), String> {
+    let hud = Hud {
+        species_count: 0,
+        generation: 0,
+    };
+    fs::write(path, svg::snapshot(simulation, usize::MAX, &hud)).map_err(|err| err.to_string())
+}
+
+/// Plays a game to its end, saving the frame of `--snapshot-tick` if `--snapshot` is given
+fn play(simulation: &mut Simulation, args: &Args) -> Result<(), String> {
+    let mut snapshot = args.snapshot.as_deref();
+    while !simulation.ended() {
+        if simulation.ticks() >= args.snapshot_tick {
+            if let Some(path) = snapshot.take() {
+                save_snapshot(simulation, path)?;
+            }
+        }
+        simulation.step();
+    }
+    if let Some(path) = snapshot {
+        eprintln!(
+            "The game ended at tick {}, saving its last frame",
+            simulation.ticks()
+        );
+        save_snapshot(simulation, path)?;
+    }
+    Ok(())
+}
+
+/// Plays a replay back headless and checks every bird gets its recorded score
+fn play_replay(path: &Path, args: &Args) -> Result<(), String> {
+    let serialized = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let replay = Replay::from_json(&serialized)?;
+    let mut simulation = Simulation::from_replay(&replay)?;
+    play(&mut simulation, args)?;
+    let mut mismatches = 0;
+    for (index, (bird, score)) in replay.birds.iter().zip(&simulation.scores).enumerate() {
+        if bird.score != *score {
+            mismatches += 1;
+            eprintln!("Bird {}: scored {}, recorded {}", index, score, bird.score);
+        }
+    }
+    println!(
+        "{} birds replayed, {} mismatches",
+        replay.birds.len(),
+        mismatches
+    );
+    Ok(())
+}
+
+/// Plays saved champions headless, a champion may never die so the game is capped to
+/// `MAX_TICKS` when `--max-ticks` isn't given
+fn play_champions(path: &Path, args: &Args) -> Result<(), String> {
+    const MAX_TICKS: usize = 100_000;
+
+    let serialized = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let champions = Champions::from_json(&serialized)?;
+    let mode = champions.mode()?;
+    let sensors = champions.sensors()?;
+    let networks = champions.networks();
+    let (width, height, hole_size, speed) = (
+        args.params.width,
+        args.params.height,
+        args.params.hole_size,
+        args.params.speed,
+    );
+    let seed = args.params.seed as u64;
+    let mut simulation = Simulation::new(mode, width, height, hole_size, speed, seed);
+    simulation.set_sensors(sensors);
+    simulation.set_fitness(args.params.fitness());
+    simulation.set_physics(args.params.physics_for(mode));
+    simulation.set_moving_pipes(args.params.pipe_amplitude, args.params.pipe_frequency);
+    let max_ticks = match args.params.max_ticks {
+        0 => MAX_TICKS,
+        max_ticks => max_ticks as usize,
+    };
+    simulation.set_limits(max_ticks, args.params.max_pipes as usize);
+    simulation.init(networks);
+    play(&mut simulation, args)?;
+    for (index, score) in simulation.scores.iter().enumerate() {
+        println!("Champion {}: {}", index, score);
+    }
+    if simulation.capped() {
+        println!(
+            "Stopped after {} ticks and {} pipes with champions still alive",
+            simulation.ticks(),
+            simulation.current_score()
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    if let Some(path) = &args.play_replay {
+        if let Err(err) = play_replay(path, &args) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Some(path) = &args.play_champions {
+        if let Err(err) = play_champions(path, &args) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    eprintln!("Seed: {}", args.params.seed);
+    let mut sim = TrainingSimulation::new(args.params.clone());
+    if let Some(dir) = args.replay_dir.clone() {
+        fs::create_dir_all(&dir).unwrap();
+        let mut generation = 0;
+        sim.on_replay(Box::new(move |replay| {
+            let path = dir.join(format!("generation-{}.json", generation));
+            fs::write(path, replay.to_json()).unwrap();
+            generation += 1;
+        }));
+    }
+    let mut stats_file = args.stats.as_ref().map(|path| {
+        let csv = path.extension().is_some_and(|extension| extension == "csv");
+        let mut file = File::create(path).unwrap();
+        if csv {
+            writeln!(file, "{}", Statistics::csv_header()).unwrap();
+        }
+        (file, csv)
+    });
+    sim.on_generation(Box::new(move |stats| {
+        eprintln!(
+            "Generation {}: best {}, mean {:.2}, median {}, species {}{}",
+            stats.generation,
+            stats.best,
+            stats.mean,
+            stats.median,
+            stats.species_count,
+            if stats.capped { " (capped)" } else { "" }
+        );
+        if let Some((file, csv)) = &mut stats_file {
+            let line = if *csv {
+                Statistics::csv_line(stats)
+            } else {
+                Statistics::json_line(stats)
+            };
+            writeln!(file, "{}", line).unwrap();
+        }
+    }));
+
+    {
+        let mut runner: Train<TrainingSimulation, f64> = Train::new(&mut sim);
+
+        configure_training(&mut runner, &args.params);
+        runner.start().unwrap();
+    }
+
+    if let (Some(path), Some(champions)) = (&args.save_champions, sim.champions()) {
+        fs::write(path, champions.to_json()).unwrap();
+        eprintln!("Champions saved to {}", path.display());
+    }
+    if let Some(path) = &args.chart {
+        let chart = chart::draw_chart(sim.statistics().generations(), chart::WIDTH, chart::HEIGHT);
+        fs::write(path, svg::to_svg(&chart, chart::WIDTH, chart::HEIGHT)).unwrap();
+        eprintln!("Chart saved to {}", path.display());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::game::mode::GameMode;
+use crate::game::network::NetworkLayout;
+use crate::game::sensors::Sensors;
+use neat_gru::neural_network::NeuralNetwork;
+use neat_gru::topology::Topology;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+/// Bumped whenever the format changes in a way older readers can't handle
+pub const CHAMPIONS_VERSION: u32 = 1;
+
+/// Number of topologies kept at the end of a training
+pub const CHAMPIONS_COUNT: usize = 10;
+
+/// Best networks of a training, as saved on disk or downloaded
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Champions {
+    pub version: u32,
+    pub game_type: i32,
+    /// Mask of the sensors the networks were trained with
+    #[serde(default = "default_sensors")]
+    pub sensors: u32,
+    pub champions: Vec<Champion>,
+}
+
+fn default_sensors() -> u32 {
+    Sensors::default().mask()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Champion {
+    pub score: f64,
+    /// Serialized by `neat_gru`, read back with `Topology::from_string`
+    pub topology: serde_json::Value,
+}
+
+impl Champions {
+    /// Keeps the `count` best topologies of the training history.
+    ///
+    /// `neat_gru` pushes the best topology of every species to the history each generation, so
+    /// the same network is there many times and only kept once
+    pub fn new(
+        mode: GameMode,
+        sensors: Sensors,
+        history: &[Topology<f64>],
+        count: usize,
+    ) -> Champions {
+        let mut best: Vec<&Topology<f64>> = history.iter().collect();
+        best.sort_by(|a, b| {
+            b.get_last_result()
+                .partial_cmp(&a.get_last_result())
+                .unwrap()
+        });
+        let mut seen = HashSet::new();
+        let champions = best
+            .into_iter()
+            .map(|topology| (topology.get_last_result(), topology.to_string()))
+            .filter(|(_, serialized)| seen.insert(serialized.clone()))
+            .take(count)
+            .map(|(score, serialized)| Champion {
+                score,
+                topology: serde_json::from_str(&serialized).unwrap(),
+            })
+            .collect();
+        Champions {
+            version: CHAMPIONS_VERSION,
+            game_type: mode.id(),
+            sensors: sensors.mask(),
+            champions,
+        }
+    }
+
+    pub fn from_json(serialized: &str) -> Result<Champions, String> {
+        let champions: Champions =
+            serde_json::from_str(serialized).map_err(|err| err.to_string())?;
+        if champions.version != CHAMPIONS_VERSION {
+            return Err(format!(
+                "Unsupported champions version {}, expected {}",
+                champions.version, CHAMPIONS_VERSION
+            ));
+        }
+        champions.mode()?;
+        let sensors = champions.sensors()?;
+        for champion in &champions.champions {
+            // Also makes sure `NeuralNetwork::from_string` can read the topology, and
+            // `NeuralNetwork::compute` writes the inputs without bounds checks
+            let layout = NetworkLayout::from_topology(&champion.topology)?;
+            let inputs = layout.layers()[0];
+            if inputs != sensors.len() {
+                return Err(format!(
+                    "The networks have {} inputs but {} sensors are selected",
+                    inputs,
+                    sensors.len()
+                ));
+            }
+        }
+        Ok(champions)
+    }
+
+    pub fn mode(&self) -> Result<GameMode, String> {
+        GameMode::try_from(self.game_type)
+    }
+
+    pub fn sensors(&self) -> Result<Sensors, String> {
+        Sensors::from_mask(self.sensors)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Structure of the network of every champion, the best first
+    pub fn layouts(&self) -> Result<Vec<NetworkLayout>, String> {
+        self.champions
+            .iter()
+            .map(|champion| NetworkLayout::from_topology(&champion.topology))
+            .collect()
+    }
+
+    /// Builds a network for every champion, the best first
+    pub fn networks(&self) -> Vec<NeuralNetwork<f64>> {
+        self.champions
+            .iter()
+            .map(|champion| NeuralNetwork::from_string(&champion.topology.to_string()))
+            .collect()
+    }
+}
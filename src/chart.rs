@@ -0,0 +1,124 @@
+use crate::game::draw::DrawList;
+use crate::statistics::GenerationStats;
+
+/// Size the chart is drawn at, canvases scale it to their own size
+pub const WIDTH: f64 = 700.0;
+pub const HEIGHT: f64 = 300.0;
+
+const MARGIN_X: f64 = 60.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+
+const BEST_COLOR: &str = "rgb(39, 174, 96)";
+const MEAN_COLOR: &str = "rgb(41, 128, 185)";
+const SPECIES_COLOR: &str = "rgb(230, 126, 34)";
+
+/// Area of the chart the curves are drawn in
+struct Plot {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    generations: usize,
+}
+
+impl Plot {
+    /// Point of a generation for a value on a scale going from 0 to `max`
+    fn point(&self, generation: usize, value: f64, max: f64) -> (f64, f64) {
+        let x = if self.generations > 1 {
+            self.x + self.width * generation as f64 / (self.generations - 1) as f64
+        } else {
+            self.x
+        };
+        (x, self.y + self.height * (1.0 - value / max))
+    }
+
+    fn curve(&self, values: &[f64], max: f64, color: &str, draw: &mut DrawList) {
+        draw.begin_path();
+        draw.set_stroke_style(color);
+        draw.set_line_width(2.0);
+        for (generation, &value) in values.iter().enumerate() {
+            let (x, y) = self.point(generation, value, max);
+            if generation == 0 {
+                draw.move_to(x, y);
+            } else {
+                draw.line_to(x, y);
+            }
+        }
+        draw.stroke();
+    }
+}
+
+/// Upper bound of a scale, 1 when every value is 0 so that the curves stay on the chart
+fn scale_max(values: impl Iterator<Item = f64>) -> f64 {
+    let max = values.fold(0.0, f64::max);
+    if max > 0.0 {
+        max
+    } else {
+        1.0
+    }
+}
+
+/// Draws the best and mean fitness of every generation on the left scale and the number of
+/// species on the right one
+pub fn draw_chart(generations: &[GenerationStats], width: f64, height: f64) -> DrawList {
+    let mut draw = DrawList::new();
+    draw.clear_rect(0.0, 0.0, width, height);
+    let plot = Plot {
+        x: MARGIN_X,
+        y: MARGIN_TOP,
+        width: width - 2.0 * MARGIN_X,
+        height: height - MARGIN_TOP - MARGIN_BOTTOM,
+        generations: generations.len(),
+    };
+
+    draw.begin_path();
+    draw.set_stroke_style("black");
+    draw.set_line_width(1.0);
+    draw.move_to(plot.x, plot.y);
+    draw.line_to(plot.x, plot.y + plot.height);
+    draw.line_to(plot.x + plot.width, plot.y + plot.height);
+    draw.line_to(plot.x + plot.width, plot.y);
+    draw.stroke();
+
+    let fitness_max = scale_max(generations.iter().map(|stats| stats.best));
+    let species_max = scale_max(generations.iter().map(|stats| stats.species_count as f64));
+    draw.set_font("12px Arial");
+    draw.set_fill_style("black");
+    draw.fill_text(&format!("{:.0}", fitness_max), 5.0, plot.y + 4.0);
+    draw.fill_text("0", plot.x - 15.0, plot.y + plot.height + 4.0);
+    draw.fill_text(
+        &format!("{}", species_max),
+        plot.x + plot.width + 5.0,
+        plot.y + 4.0,
+    );
+    draw.fill_text(
+        &format!("Generation {}", generations.len()),
+        plot.x + plot.width - 90.0,
+        plot.y + plot.height + 20.0,
+    );
+
+    let legend = [
+        ("Best fitness", BEST_COLOR),
+        ("Mean fitness", MEAN_COLOR),
+        ("Species", SPECIES_COLOR),
+    ];
+    for (index, (name, color)) in legend.iter().enumerate() {
+        draw.set_fill_style(color);
+        draw.fill_text(name, plot.x + 110.0 * index as f64, 20.0);
+    }
+
+    if generations.is_empty() {
+        return draw;
+    }
+    let best: Vec<f64> = generations.iter().map(|stats| stats.best).collect();
+    let mean: Vec<f64> = generations.iter().map(|stats| stats.mean).collect();
+    let species: Vec<f64> = generations
+        .iter()
+        .map(|stats| stats.species_count as f64)
+        .collect();
+    plot.curve(&best, fitness_max, BEST_COLOR, &mut draw);
+    plot.curve(&mean, fitness_max, MEAN_COLOR, &mut draw);
+    plot.curve(&species, species_max, SPECIES_COLOR, &mut draw);
+    draw
+}
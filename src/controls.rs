@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use wasm_bindgen::prelude::*;
+
+static TICKS_PER_FRAME: AtomicU32 = AtomicU32::new(1);
+static RENDERING: AtomicBool = AtomicBool::new(true);
+static NETWORK_OVERLAY: AtomicBool = AtomicBool::new(false);
+static PAUSED: AtomicBool = AtomicBool::new(false);
+/// Ticks `step` asked for and the game loop hasn't run yet
+static PENDING_STEPS: AtomicU32 = AtomicU32::new(0);
+
+/// Sets how many simulation ticks run before each animation frame, can change at any time
+#[wasm_bindgen]
+pub fn set_ticks_per_frame(ticks: u32) {
+    TICKS_PER_FRAME.store(ticks.max(1), Ordering::Relaxed);
+}
+
+/// Enables or disables drawing the world, the HUD is always drawn
+#[wasm_bindgen]
+pub fn set_rendering(enabled: bool) {
+    RENDERING.store(enabled, Ordering::Relaxed);
+}
+
+/// Shows or hides the network of the leading bird over the canvas
+#[wasm_bindgen]
+pub fn set_network_overlay(enabled: bool) {
+    NETWORK_OVERLAY.store(enabled, Ordering::Relaxed);
+}
+
+/// Freezes the game on its current frame
+#[wasm_bindgen]
+pub fn pause() {
+    PAUSED.store(true, Ordering::Relaxed);
+}
+
+/// Resumes a paused game, dropping the steps it hasn't run yet
+#[wasm_bindgen]
+pub fn resume() {
+    PAUSED.store(false, Ordering::Relaxed);
+    PENDING_STEPS.store(0, Ordering::Relaxed);
+}
+
+/// Whether the game is paused
+#[wasm_bindgen]
+pub fn paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Pauses the game and runs `ticks` more ticks on the next frame
+#[wasm_bindgen]
+pub fn step(ticks: u32) {
+    PAUSED.store(true, Ordering::Relaxed);
+    PENDING_STEPS.fetch_add(ticks, Ordering::Relaxed);
+}
+
+/// Ticks to run while paused, resets the count
+pub fn take_steps() -> u32 {
+    PENDING_STEPS.swap(0, Ordering::Relaxed)
+}
+
+pub fn ticks_per_frame() -> u32 {
+    TICKS_PER_FRAME.load(Ordering::Relaxed)
+}
+
+pub fn rendering() -> bool {
+    RENDERING.load(Ordering::Relaxed)
+}
+
+pub fn network_overlay() -> bool {
+    NETWORK_OVERLAY.load(Ordering::Relaxed)
+}
@@ -0,0 +1,200 @@
+use crate::statistics::GenerationStats;
+use crate::GameParams;
+use std::convert::TryFrom;
+
+/// How the hole size goes from `curriculum_start` down to `hole_size` during a training
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Schedule {
+    /// Always `hole_size`
+    #[default]
+    Fixed,
+    /// Shrinks a bit every generation and reaches `hole_size` after `curriculum_generations`
+    Linear,
+    /// Shrinks by `curriculum_step` every `curriculum_generations`
+    Step,
+    /// Shrinks by `curriculum_step` whenever a generation passes `curriculum_threshold` pipes
+    Performance,
+}
+
+pub const ALL_SCHEDULES: [Schedule; 4] = [
+    Schedule::Fixed,
+    Schedule::Linear,
+    Schedule::Step,
+    Schedule::Performance,
+];
+
+impl Schedule {
+    /// Identifier used by `GameParams`
+    pub fn id(self) -> i32 {
+        match self {
+            Schedule::Fixed => 0,
+            Schedule::Linear => 1,
+            Schedule::Step => 2,
+            Schedule::Performance => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Schedule::Fixed => "fixed",
+            Schedule::Linear => "linear",
+            Schedule::Step => "step",
+            Schedule::Performance => "performance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Schedule, String> {
+        ALL_SCHEDULES
+            .iter()
+            .cloned()
+            .find(|schedule| schedule.name() == name)
+            .ok_or(format!("Unknown curriculum: {}", name))
+    }
+}
+
+impl TryFrom<i32> for Schedule {
+    type Error = String;
+
+    fn try_from(id: i32) -> Result<Schedule, String> {
+        ALL_SCHEDULES
+            .iter()
+            .cloned()
+            .find(|schedule| schedule.id() == id)
+            .ok_or(format!("Invalid curriculum: {}", id))
+    }
+}
+
+/// Hole size of every generation, starting wide and narrowing as the population improves
+#[derive(Clone, Debug)]
+pub struct Curriculum {
+    schedule: Schedule,
+    start: i32,
+    target: i32,
+    generations: u32,
+    step: i32,
+    threshold: f64,
+    hole_size: i32,
+}
+
+impl Curriculum {
+    /// `params` must have been validated
+    pub fn new(params: &GameParams) -> Curriculum {
+        let schedule = Schedule::try_from(params.curriculum).unwrap();
+        let hole_size = match schedule {
+            Schedule::Fixed => params.hole_size,
+            _ => params.curriculum_start,
+        };
+        Curriculum {
+            schedule,
+            start: params.curriculum_start,
+            target: params.hole_size,
+            generations: params.curriculum_generations,
+            step: params.curriculum_step,
+            threshold: params.curriculum_threshold,
+            hole_size,
+        }
+    }
+
+    /// Hole size of the next generation
+    pub fn hole_size(&self) -> i32 {
+        self.hole_size
+    }
+
+    /// Updates the hole size once a generation is over
+    pub fn advance(&mut self, stats: &GenerationStats) {
+        let finished = stats.generation as i64 + 1;
+        let hole_size = match self.schedule {
+            Schedule::Fixed => self.target,
+            Schedule::Linear => {
+                let progress = (finished as f64 / self.generations as f64).min(1.0);
+                let shrink = (self.start - self.target) as f64 * progress;
+                self.start - shrink.round() as i32
+            }
+            Schedule::Step => {
+                let steps = finished / self.generations as i64;
+                (self.start as i64 - steps * self.step as i64) as i32
+            }
+            Schedule::Performance if stats.pipes_passed >= self.threshold => {
+                self.hole_size - self.step
+            }
+            Schedule::Performance => self.hole_size,
+        };
+        self.hole_size = hole_size.max(self.target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Curriculum going from a 300 pixels hole down to 200
+    fn curriculum(schedule: Schedule, generations: u32, step: i32, threshold: f64) -> Curriculum {
+        let mut params = GameParams::new(0, 10, 1, 200);
+        params.curriculum = schedule.id();
+        params.curriculum_start = 300;
+        params.curriculum_generations = generations;
+        params.curriculum_step = step;
+        params.curriculum_threshold = threshold;
+        params.validate().unwrap();
+        Curriculum::new(&params)
+    }
+
+    fn generation(generation: usize, pipes_passed: f64) -> GenerationStats {
+        GenerationStats::new(generation, &[1.0], 1, pipes_passed, 100, 0, false)
+    }
+
+    /// Hole sizes after each generation, given the pipes passed by each
+    fn hole_sizes(curriculum: &mut Curriculum, pipes_passed: &[f64]) -> Vec<i32> {
+        pipes_passed
+            .iter()
+            .enumerate()
+            .map(|(index, &pipes)| {
+                curriculum.advance(&generation(index, pipes));
+                curriculum.hole_size()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_keeps_the_hole_size() {
+        let mut curriculum = curriculum(Schedule::Fixed, 10, 10, 5.0);
+        assert_eq!(curriculum.hole_size(), 200);
+        assert_eq!(hole_sizes(&mut curriculum, &[0.0; 3]), vec![200; 3]);
+    }
+
+    #[test]
+    fn linear_reaches_the_target_after_the_generations() {
+        let mut curriculum = curriculum(Schedule::Linear, 4, 10, 5.0);
+        assert_eq!(curriculum.hole_size(), 300);
+        assert_eq!(
+            hole_sizes(&mut curriculum, &[0.0; 6]),
+            vec![275, 250, 225, 200, 200, 200]
+        );
+    }
+
+    #[test]
+    fn step_shrinks_every_generations() {
+        let mut curriculum = curriculum(Schedule::Step, 3, 10, 5.0);
+        assert_eq!(
+            hole_sizes(&mut curriculum, &[0.0; 7]),
+            vec![300, 300, 290, 290, 290, 280, 280]
+        );
+    }
+
+    #[test]
+    fn performance_only_shrinks_past_the_threshold() {
+        let mut curriculum = curriculum(Schedule::Performance, 10, 30, 5.0);
+        assert_eq!(
+            hole_sizes(&mut curriculum, &[4.9, 5.0, 0.0, 7.0, 5.0]),
+            vec![300, 270, 270, 240, 210]
+        );
+    }
+
+    #[test]
+    fn never_goes_below_the_hole_size() {
+        let mut step = curriculum(Schedule::Step, 1, 60, 5.0);
+        assert_eq!(hole_sizes(&mut step, &[0.0; 3]), vec![240, 200, 200]);
+        let mut performance = curriculum(Schedule::Performance, 10, 60, 5.0);
+        assert_eq!(hole_sizes(&mut performance, &[9.0; 3]), vec![240, 200, 200]);
+    }
+}
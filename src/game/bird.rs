@@ -1,138 +1,165 @@
-use crate::game::game::Render;
+use crate::game::draw::{DrawList, Render};
+use crate::game::mode::GameMode;
+use crate::game::network::NetworkLayout;
+use crate::game::physics::Physics;
 use neat_gru::neural_network::NeuralNetwork;
-use wasm_bindgen::JsValue;
 
 pub const RADIUS: f64 = 30.0;
 pub const X: f64 = 45.0;
+/// Index given to the bird controlled by a human
+pub const PLAYER_INDEX: usize = usize::MAX;
 
-pub struct Bird<const GAME_TYPE: i32> {
+pub struct Bird {
     pub index: usize,
+    mode: GameMode,
     pub y: f64,
+    /// `y` before the last tick, collisions are checked along the way in between
+    pub previous_y: f64,
     color: String,
     pub velocity: f64,
+    pub ticks_since_flap: usize,
     net: Option<NeuralNetwork<f64>>,
+    /// Structure of `net` when its topology is known
+    layout: Option<NetworkLayout>,
+    /// Values given to and computed by `net` on the last decision
+    inputs: Vec<f64>,
+    outputs: Vec<f64>,
 }
 
-impl<const GAME_TYPE: i32> Bird<{ GAME_TYPE }> {
-    pub fn new(index: usize, color: String, net: NeuralNetwork<f64>) -> Bird<{ GAME_TYPE }> {
+impl Bird {
+    /// Creates a bird at height `y`, driven by `net`
+    pub fn new(
+        index: usize,
+        mode: GameMode,
+        y: f64,
+        color: String,
+        net: NeuralNetwork<f64>,
+    ) -> Bird {
         Bird {
             index,
+            mode,
             color,
             net: Some(net),
-            y: 400.0,
+            layout: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            y,
+            previous_y: y,
             velocity: 0.0,
+            ticks_since_flap: 0,
         }
     }
 
     /// Creates a bird without a nn
-    pub fn new_without_handler(index: usize, color: String) -> Bird<{ GAME_TYPE }> {
+    pub fn new_without_handler(index: usize, mode: GameMode, y: f64, color: String) -> Bird {
         Bird {
             index,
+            mode,
             color,
             net: None,
-            y: 400.0,
+            layout: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            y,
+            previous_y: y,
             velocity: 0.0,
+            ticks_since_flap: 0,
         }
     }
 
     /// Makes the bird fall based on it's own velocity
-    pub fn y_velocity(&mut self) {
+    pub fn y_velocity(&mut self, physics: &Physics) {
+        self.previous_y = self.y;
         self.y -= self.velocity;
-        self.velocity -= 0.5;
+        self.velocity -= physics.gravity;
+        self.ticks_since_flap += 1;
     }
 
-    pub fn jump(&mut self) {
-        if GAME_TYPE == 0 {
-            self.velocity = 10.0;
-        } else if GAME_TYPE == 1 {
-            self.velocity += 1.0;
-        }
+    pub fn jump(&mut self, physics: &Physics) {
+        self.ticks_since_flap = 0;
+        self.velocity = self.mode.jump(self.velocity, physics.jump_impulse);
+    }
+
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    pub fn layout(&self) -> Option<&NetworkLayout> {
+        self.layout.as_ref()
+    }
+
+    pub fn set_layout(&mut self, layout: NetworkLayout) {
+        self.layout = Some(layout);
+    }
+
+    /// Inputs of the last decision
+    pub fn network_inputs(&self) -> &[f64] {
+        &self.inputs
     }
 
-    /// Executes a decision based on given input
-    pub fn make_decision(&mut self, inputs: &[f64]) {
-        let output = self.net.as_mut().unwrap().compute(inputs);
-        // We can use the very useful Rust Pattern matching here
-        match GAME_TYPE {
-            0 if output[0] >= 0.0 => self.jump(),
-            1 if output[0] >= 0.0 => self.jump(),
-            _ => return (),
-        };
+    /// Outputs of the last decision
+    pub fn network_outputs(&self) -> &[f64] {
+        &self.outputs
+    }
+
+    /// Executes a decision based on given input, returns whether the bird jumped
+    pub fn make_decision(&mut self, inputs: &[f64], physics: &Physics) -> bool {
+        self.outputs = self.net.as_mut().unwrap().compute(inputs);
+        self.inputs.clear();
+        self.inputs.extend_from_slice(inputs);
+        if !self.mode.should_jump(&self.outputs) {
+            return false;
+        }
+        self.jump(physics);
+        true
     }
 }
 
-impl<const GAME_TYPE: i32> Render for Bird<{ GAME_TYPE }> {
-    fn render(&self, canvas_ctx: &web_sys::CanvasRenderingContext2d) {
-        let black = JsValue::from_str("black");
-        let is_player = self.net.is_none();
-        canvas_ctx.begin_path();
-        canvas_ctx.set_line_width(5.0);
-        canvas_ctx.set_fill_style(&JsValue::from_str(&*self.color));
-        canvas_ctx
-            .arc(X, self.y, RADIUS, 0.0, std::f64::consts::PI * 2.0)
-            .unwrap();
-        canvas_ctx.fill();
+impl Render for Bird {
+    fn render(&self, draw: &mut DrawList) {
+        let is_player = self.index == PLAYER_INDEX;
+        draw.begin_path();
+        draw.set_line_width(5.0);
+        draw.set_fill_style(&self.color);
+        draw.circle(X, self.y, RADIUS);
+        draw.fill();
         // Eye
-        canvas_ctx.begin_path();
-        canvas_ctx.set_line_width(5.0);
-        canvas_ctx.set_fill_style(&JsValue::from_str("white"));
-        canvas_ctx
-            .arc(
-                X + RADIUS / 3.0,
-                self.y - RADIUS / 2.0,
-                RADIUS / 2.0,
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap();
-        canvas_ctx.fill();
+        draw.begin_path();
+        draw.set_line_width(5.0);
+        draw.set_fill_style("white");
+        draw.circle(X + RADIUS / 3.0, self.y - RADIUS / 2.0, RADIUS / 2.0);
+        draw.fill();
 
         // Eye dot
-        canvas_ctx.begin_path();
-        canvas_ctx
-            .arc(
-                X + RADIUS / 2.0,
-                self.y - RADIUS / 2.0,
-                5.0,
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap();
-        canvas_ctx.set_fill_style(&black);
-        canvas_ctx.fill();
+        draw.begin_path();
+        draw.circle(X + RADIUS / 2.0, self.y - RADIUS / 2.0, 5.0);
+        draw.set_fill_style("black");
+        draw.fill();
         // Mouth
-        canvas_ctx.begin_path();
+        draw.begin_path();
         if !is_player {
-            canvas_ctx.set_fill_style(&JsValue::from_str("#f76946"));
+            draw.set_fill_style("#f76946");
         }
-        canvas_ctx
-            .ellipse(
-                X + RADIUS / 1.2,
-                self.y + 5.0,
-                RADIUS / 2.0,
-                RADIUS / 2.8,
-                0.0,
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap();
-        canvas_ctx.fill();
+        draw.ellipse(
+            X + RADIUS / 1.2,
+            self.y + 5.0,
+            RADIUS / 2.0,
+            RADIUS / 2.8,
+            0.0,
+        );
+        draw.fill();
         // Wing
-        canvas_ctx.begin_path();
+        draw.begin_path();
         if !is_player {
-            canvas_ctx.set_fill_style(&JsValue::from_str("#f7ea25"));
+            draw.set_fill_style("#f7ea25");
         }
-        canvas_ctx
-            .ellipse(
-                X - RADIUS / 1.5,
-                self.y + RADIUS / 2.0,
-                RADIUS / 2.0,
-                RADIUS / 3.0,
-                std::f64::consts::PI * 1.9,
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap();
-        canvas_ctx.fill();
+        draw.ellipse(
+            X - RADIUS / 1.5,
+            self.y + RADIUS / 2.0,
+            RADIUS / 2.0,
+            RADIUS / 3.0,
+            std::f64::consts::PI * 1.9,
+        );
+        draw.fill();
     }
 }
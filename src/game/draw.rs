@@ -0,0 +1,313 @@
+use crate::game::network::NetworkLayout;
+use crate::game::simulation::Simulation;
+use wasm_bindgen::JsValue;
+
+/// Something that can be drawn as a list of `DrawCommand`s
+pub trait Render {
+    fn render(&self, draw: &mut DrawList);
+}
+
+/// A drawing operation, named after the canvas API it replays on.
+///
+/// Shapes are added to the current path, `Fill` and `Stroke` paint every shape added since the
+/// last `BeginPath`
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    BeginPath,
+    FillStyle(String),
+    StrokeStyle(String),
+    LineWidth(f64),
+    Font(String),
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+    Arc {
+        x: f64,
+        y: f64,
+        radius: f64,
+        start_angle: f64,
+        end_angle: f64,
+    },
+    Ellipse {
+        x: f64,
+        y: f64,
+        radius_x: f64,
+        radius_y: f64,
+        rotation: f64,
+        start_angle: f64,
+        end_angle: f64,
+    },
+    MoveTo {
+        x: f64,
+        y: f64,
+    },
+    LineTo {
+        x: f64,
+        y: f64,
+    },
+    Fill,
+    Stroke,
+    FillText {
+        text: String,
+        x: f64,
+        y: f64,
+    },
+    ClearRect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+}
+
+/// Commands of a frame, in drawing order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawList {
+    commands: Vec<DrawCommand>,
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList::default()
+    }
+
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    pub fn push(&mut self, command: DrawCommand) {
+        self.commands.push(command);
+    }
+
+    pub fn begin_path(&mut self) {
+        self.push(DrawCommand::BeginPath);
+    }
+
+    pub fn set_fill_style(&mut self, style: &str) {
+        self.push(DrawCommand::FillStyle(String::from(style)));
+    }
+
+    pub fn set_stroke_style(&mut self, style: &str) {
+        self.push(DrawCommand::StrokeStyle(String::from(style)));
+    }
+
+    pub fn set_line_width(&mut self, width: f64) {
+        self.push(DrawCommand::LineWidth(width));
+    }
+
+    pub fn set_font(&mut self, font: &str) {
+        self.push(DrawCommand::Font(String::from(font)));
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.push(DrawCommand::Rect {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    /// A full circle
+    pub fn circle(&mut self, x: f64, y: f64, radius: f64) {
+        self.push(DrawCommand::Arc {
+            x,
+            y,
+            radius,
+            start_angle: 0.0,
+            end_angle: std::f64::consts::PI * 2.0,
+        });
+    }
+
+    /// A full ellipse rotated by `rotation` radians
+    pub fn ellipse(&mut self, x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64) {
+        self.push(DrawCommand::Ellipse {
+            x,
+            y,
+            radius_x,
+            radius_y,
+            rotation,
+            start_angle: 0.0,
+            end_angle: std::f64::consts::PI * 2.0,
+        });
+    }
+
+    /// Starts a new sub-path at the given point
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        self.push(DrawCommand::MoveTo { x, y });
+    }
+
+    /// Adds a line from the last point to the given one
+    pub fn line_to(&mut self, x: f64, y: f64) {
+        self.push(DrawCommand::LineTo { x, y });
+    }
+
+    pub fn fill(&mut self) {
+        self.push(DrawCommand::Fill);
+    }
+
+    pub fn stroke(&mut self) {
+        self.push(DrawCommand::Stroke);
+    }
+
+    pub fn fill_text(&mut self, text: &str, x: f64, y: f64) {
+        self.push(DrawCommand::FillText {
+            text: String::from(text),
+            x,
+            y,
+        });
+    }
+
+    pub fn clear_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.push(DrawCommand::ClearRect {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    /// Canvas backend, replays the commands on a browser canvas
+    pub fn draw_on_canvas(&self, canvas_ctx: &web_sys::CanvasRenderingContext2d) {
+        for command in &self.commands {
+            match command {
+                DrawCommand::BeginPath => canvas_ctx.begin_path(),
+                DrawCommand::FillStyle(style) => {
+                    canvas_ctx.set_fill_style(&JsValue::from_str(style))
+                }
+                DrawCommand::StrokeStyle(style) => {
+                    canvas_ctx.set_stroke_style(&JsValue::from_str(style))
+                }
+                DrawCommand::LineWidth(width) => canvas_ctx.set_line_width(*width),
+                DrawCommand::Font(font) => canvas_ctx.set_font(font),
+                DrawCommand::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => canvas_ctx.rect(*x, *y, *width, *height),
+                DrawCommand::Arc {
+                    x,
+                    y,
+                    radius,
+                    start_angle,
+                    end_angle,
+                } => canvas_ctx
+                    .arc(*x, *y, *radius, *start_angle, *end_angle)
+                    .unwrap(),
+                DrawCommand::Ellipse {
+                    x,
+                    y,
+                    radius_x,
+                    radius_y,
+                    rotation,
+                    start_angle,
+                    end_angle,
+                } => canvas_ctx
+                    .ellipse(
+                        *x,
+                        *y,
+                        *radius_x,
+                        *radius_y,
+                        *rotation,
+                        *start_angle,
+                        *end_angle,
+                    )
+                    .unwrap(),
+                DrawCommand::MoveTo { x, y } => canvas_ctx.move_to(*x, *y),
+                DrawCommand::LineTo { x, y } => canvas_ctx.line_to(*x, *y),
+                DrawCommand::Fill => canvas_ctx.fill(),
+                DrawCommand::Stroke => canvas_ctx.stroke(),
+                DrawCommand::FillText { text, x, y } => canvas_ctx.fill_text(text, *x, *y).unwrap(),
+                DrawCommand::ClearRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => canvas_ctx.clear_rect(*x, *y, *width, *height),
+            }
+        }
+    }
+}
+
+/// Counters drawn over the world
+pub struct Hud {
+    pub species_count: usize,
+    pub generation: usize,
+}
+
+/// Draws the network of the leading bird in the top left corner
+pub fn draw_network_overlay(simulation: &Simulation, draw: &mut DrawList) {
+    if let Some(bird) = simulation.leader() {
+        let layout = bird.layout().cloned().unwrap_or_else(|| {
+            NetworkLayout::inputs_outputs(bird.network_inputs().len(), bird.network_outputs().len())
+        });
+        layout.draw(
+            bird.network_inputs(),
+            bird.network_outputs(),
+            10.0,
+            50.0,
+            300.0,
+            220.0,
+            draw,
+        );
+    }
+}
+
+/// Draws the world with at most `render_count` birds, and the player's bird if any
+pub fn draw_world(simulation: &Simulation, render_count: usize, draw: &mut DrawList) {
+    draw.clear_rect(0.0, 0.0, simulation.width(), simulation.height());
+    for bird in simulation.birds().iter().take(render_count) {
+        bird.render(draw);
+    }
+    if let Some(player_bird) = simulation.player() {
+        player_bird.render(draw);
+    }
+    for pipe in simulation.pipes() {
+        pipe.render(draw);
+    }
+}
+
+/// Draws the score and the counters
+pub fn draw_hud(simulation: &Simulation, hud: &Hud, draw: &mut DrawList) {
+    let width = simulation.width();
+    let height = simulation.height();
+    draw.set_font("30px Arial");
+    draw.set_fill_style("black");
+    draw.fill_text(
+        &format!("{}", simulation.current_score()),
+        width / 2.0 - 30.0,
+        30.0,
+    );
+    draw.fill_text(
+        &format!("Hole size: {}", simulation.hole_size()),
+        width / 2.0 - 85.0,
+        height - 120.0,
+    );
+    draw.fill_text(
+        &format!("Alive: {}", simulation.birds().len()),
+        width / 2.0 - 45.0,
+        height - 90.0,
+    );
+    draw.fill_text(
+        &format!("Species: {}", hud.species_count),
+        width / 2.0 - 75.0,
+        height - 60.0,
+    );
+    draw.fill_text(
+        &format!("Generation: {}", hud.generation),
+        width / 2.0 - 90.0,
+        height - 30.0,
+    );
+}
+
+/// Draws a whole frame, the world and the HUD
+pub fn draw_frame(simulation: &Simulation, render_count: usize, hud: &Hud) -> DrawList {
+    let mut draw = DrawList::new();
+    draw_world(simulation, render_count, &mut draw);
+    draw_hud(simulation, hud, &mut draw);
+    draw
+}
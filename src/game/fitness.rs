@@ -0,0 +1,134 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// Score given for every pipe passed by `Fitness::PipeBonus`, a pipe takes about 125 ticks
+pub const PIPE_BONUS: f64 = 100.0;
+
+/// Score given by `Fitness::GapCentre` to a bird dying right in the middle of the gap
+pub const GAP_BONUS: f64 = 100.0;
+
+/// What is known about a bird when it dies
+pub struct Death {
+    /// Ticks the bird survived
+    pub ticks: usize,
+    pub pipes_passed: usize,
+    /// Vertical distance to the centre of the next hole, as a fraction of the height
+    pub gap_distance: f64,
+}
+
+/// Turns the death of a bird into its score.
+///
+/// The built-in scorings are selected with `Fitness`, any other one can be given to
+/// `Simulation::set_custom_fitness`
+pub trait FitnessFn: Send + Sync {
+    fn score(&self, death: &Death) -> f64;
+}
+
+/// Time survived
+pub struct TicksFitness;
+
+impl FitnessFn for TicksFitness {
+    fn score(&self, death: &Death) -> f64 {
+        death.ticks as f64
+    }
+}
+
+pub struct PipesPassedFitness;
+
+impl FitnessFn for PipesPassedFitness {
+    fn score(&self, death: &Death) -> f64 {
+        death.pipes_passed as f64
+    }
+}
+
+/// Time survived plus `bonus` for every pipe passed
+pub struct PipeBonusFitness {
+    pub bonus: f64,
+}
+
+impl FitnessFn for PipeBonusFitness {
+    fn score(&self, death: &Death) -> f64 {
+        death.ticks as f64 + death.pipes_passed as f64 * self.bonus
+    }
+}
+
+/// Time survived plus up to `bonus` for dying close to the centre of the gap
+pub struct GapCentreFitness {
+    pub bonus: f64,
+}
+
+impl FitnessFn for GapCentreFitness {
+    fn score(&self, death: &Death) -> f64 {
+        death.ticks as f64 + (1.0 - death.gap_distance).max(0.0) * self.bonus
+    }
+}
+
+/// Built-in fitnesses, selected by id in `GameParams` and replays
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Fitness {
+    /// Time survived, what the game always used
+    #[default]
+    Ticks,
+    PipesPassed,
+    /// Time survived plus `PIPE_BONUS` for every pipe passed
+    PipeBonus,
+    /// Time survived plus up to `GAP_BONUS` for dying close to the centre of the gap
+    GapCentre,
+}
+
+pub const ALL_FITNESSES: [Fitness; 4] = [
+    Fitness::Ticks,
+    Fitness::PipesPassed,
+    Fitness::PipeBonus,
+    Fitness::GapCentre,
+];
+
+impl Fitness {
+    /// Identifier used by `GameParams` and replays
+    pub fn id(self) -> i32 {
+        match self {
+            Fitness::Ticks => 0,
+            Fitness::PipesPassed => 1,
+            Fitness::PipeBonus => 2,
+            Fitness::GapCentre => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Fitness::Ticks => "ticks",
+            Fitness::PipesPassed => "pipes_passed",
+            Fitness::PipeBonus => "pipe_bonus",
+            Fitness::GapCentre => "gap_centre",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Fitness, String> {
+        ALL_FITNESSES
+            .iter()
+            .cloned()
+            .find(|fitness| fitness.name() == name)
+            .ok_or(format!("Unknown fitness: {}", name))
+    }
+
+    pub fn function(self) -> Arc<dyn FitnessFn> {
+        match self {
+            Fitness::Ticks => Arc::new(TicksFitness),
+            Fitness::PipesPassed => Arc::new(PipesPassedFitness),
+            Fitness::PipeBonus => Arc::new(PipeBonusFitness { bonus: PIPE_BONUS }),
+            Fitness::GapCentre => Arc::new(GapCentreFitness { bonus: GAP_BONUS }),
+        }
+    }
+}
+
+impl TryFrom<i32> for Fitness {
+    type Error = String;
+
+    fn try_from(id: i32) -> Result<Fitness, String> {
+        ALL_FITNESSES
+            .iter()
+            .cloned()
+            .find(|fitness| fitness.id() == id)
+            .ok_or(format!("Invalid fitness: {}", id))
+    }
+}
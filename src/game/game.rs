@@ -1,47 +1,39 @@
-use crate::game::bird::Bird;
-use crate::game::pipe::Pipe;
-use crate::game::{bird, pipe};
-use crate::utils::request_animation_frame;
+use crate::controls;
+use crate::game::draw::{self, DrawList, Hud};
+use crate::game::mode::GameMode;
+use crate::game::replay::Replay;
+use crate::game::simulation::Simulation;
+use crate::game::svg;
+use crate::utils::{request_animation_frame, scaled_context};
 use futures::channel::oneshot;
-use neat_gru::neural_network::NeuralNetwork;
-use rand::prelude::ThreadRng;
-use rand::Rng;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use wasm_bindgen::__rt::std::sync::Mutex;
 use wasm_bindgen::closure::Closure;
-use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen::JsCast;
 
 const SPACEBAR: u32 = 32;
 
-pub trait Render {
-    fn render(&self, canvas_ctx: &web_sys::CanvasRenderingContext2d);
-}
-
-/// Get the next pipe
-macro_rules! get_first_pipe {
-    ($self: expr) => {
-        if $self.pipes[0].x + pipe::WIDTH >= bird::X - bird::RADIUS {
-            &$self.pipes[0]
-        } else {
-            &$self.pipes[1]
-        }
-    };
+thread_local! {
+    /// Game being played on the canvas
+    static CURRENT_GAME: RefCell<Option<Arc<Mutex<Game>>>> = const { RefCell::new(None) };
 }
 
 /// Get the HtmlInputElement
 macro_rules! get_html_input_element {
-    ($document: expr, $element_name: expr) =>{
+    ($document: expr, $element_name: expr) => {
         $document
             .get_element_by_id($element_name)
             .unwrap()
             .dyn_into::<web_sys::HtmlInputElement>()
             .map_err(|_| ())
-            .unwrap();
-    }
+            .unwrap()
+    };
 }
 
-struct PlayerHandler<const GAME_TYPE: i32> {
-    bird: Bird<{ GAME_TYPE }>,
+struct PlayerHandler {
+    mode: GameMode,
     space_pressed: Arc<Mutex<bool>>,
     func_keydown: Closure<dyn FnMut(web_sys::KeyboardEvent)>,
     func_keyup: Closure<dyn FnMut(web_sys::KeyboardEvent)>,
@@ -64,8 +56,8 @@ macro_rules! handle_input {
     };
 }
 
-impl<const GAME_TYPE: i32> PlayerHandler<{ GAME_TYPE }> {
-    pub fn new() -> PlayerHandler<{ GAME_TYPE }> {
+impl PlayerHandler {
+    pub fn new(mode: GameMode) -> PlayerHandler {
         let space_pressed = Arc::new(Mutex::new(false));
         let pressed_clone = space_pressed.clone();
         let pressed_clone2 = space_pressed.clone();
@@ -100,26 +92,26 @@ impl<const GAME_TYPE: i32> PlayerHandler<{ GAME_TYPE }> {
             .unwrap();
 
         PlayerHandler {
+            mode,
             space_pressed,
             func_keydown,
             func_keyup,
             func_mousedown,
             func_mouseup,
-            bird: Bird::new_without_handler(usize::MAX, String::from("black")),
         }
     }
 
     pub fn is_pressed(&self) -> bool {
         let pressed = &mut *self.space_pressed.lock().unwrap();
         let pressed_cp = *pressed;
-        if GAME_TYPE == 0 {
+        if !self.mode.repeats_held_input() {
             *pressed = false;
         }
         pressed_cp
     }
 }
 
-impl<const GAME_TYPE: i32> Drop for PlayerHandler<{ GAME_TYPE }> {
+impl Drop for PlayerHandler {
     fn drop(&mut self) {
         let document = web_sys::window().unwrap().document().unwrap();
         document
@@ -146,143 +138,173 @@ impl<const GAME_TYPE: i32> Drop for PlayerHandler<{ GAME_TYPE }> {
     }
 }
 
-pub struct Game<const GAME_TYPE: i32> {
-    pipes: Vec<Pipe>,
-    birds: Vec<Bird<{ GAME_TYPE }>>,
-    pub scores: Vec<f64>,
-    rng: ThreadRng,
-    width: f64,
-    height: f64,
+pub struct Game {
+    simulation: Simulation,
     render_count: i32,
     species_count: usize,
     generation: usize,
-    hole_size: f64,
-    ticks: usize,
-    current_score: f64,
     pub started: bool,
-    player: Option<PlayerHandler<{ GAME_TYPE }>>,
+    /// Set by whoever started the game to end it on the next frame
+    stop: Arc<AtomicBool>,
+    player: Option<PlayerHandler>,
     canvas_ctx: Arc<Mutex<web_sys::CanvasRenderingContext2d>>,
-    speed: bool,
 }
 
-unsafe impl<const GAME_TYPE: i32> Send for Game<{ GAME_TYPE }> {}
+unsafe impl Send for Game {}
 
-unsafe impl<const GAME_TYPE: i32> Sync for Game<{ GAME_TYPE }> {}
+unsafe impl Sync for Game {}
 
-impl<const GAME_TYPE: i32> Game<{ GAME_TYPE }> {
+impl Game {
     pub fn new(
-        width: f64,
-        height: f64,
+        mut simulation: Simulation,
         render_count: i32,
         species_count: usize,
         generation: usize,
-        hole_size: i32,
         player: bool,
+        stop: Arc<AtomicBool>,
         canvas_ctx: Arc<Mutex<web_sys::CanvasRenderingContext2d>>,
-        speed: bool,
-    ) -> Game<{ GAME_TYPE }> {
-        let hole_size = hole_size as f64;
+    ) -> Game {
         let (space_pressed, started) = if player {
-            (Some(PlayerHandler::new()), false)
+            simulation.add_player();
+            (Some(PlayerHandler::new(simulation.mode())), false)
         } else {
             (None, true)
         };
 
-        let rng = rand::thread_rng();
         Game {
-            width,
-            height,
+            simulation,
             render_count,
             species_count,
-            rng,
             canvas_ctx,
             generation,
-            hole_size,
             player: space_pressed,
             started,
-            pipes: Vec::new(),
-            birds: Vec::new(),
-            scores: Vec::new(),
-            current_score: 0.0,
-            ticks: 0,
-            speed,
+            stop,
         }
     }
 
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// Plays a generation on the canvas, the `#player` and `#speed` checkboxes apply to it.
+    ///
+    /// Setting `stop` ends the game on its next frame
     pub async fn run_game(
-        width: f64,
-        height: f64,
+        mut simulation: Simulation,
         render_count: i32,
         species_count: usize,
         generation: usize,
-        hole_size: i32,
-        networks: Vec<NeuralNetwork<f64>>,
-    ) -> Arc<Mutex<Game<{ GAME_TYPE }>>> {
+        stop: Arc<AtomicBool>,
+    ) -> Arc<Mutex<Game>> {
+        let document = web_sys::window().unwrap().document().unwrap();
+        // Player Checkbox
+        let player_checkbox = get_html_input_element!(document, "player");
+        let player_checked = player_checkbox.checked();
+
+        // Speed Checkbox
+        let speed_checkbox = get_html_input_element!(document, "speed");
+        simulation.set_speed(speed_checkbox.checked());
+
+        Game::run(
+            simulation,
+            render_count,
+            species_count,
+            generation,
+            player_checked,
+            stop,
+        )
+        .await
+    }
+
+    /// Plays a recorded game back on the canvas
+    pub async fn play_replay(replay: &Replay) -> Result<Arc<Mutex<Game>>, String> {
+        let simulation = Simulation::from_replay(replay)?;
+        let render_count = replay.birds.len() as i32;
+        let stop = Arc::new(AtomicBool::new(false));
+        Ok(Game::run(simulation, render_count, 0, 0, false, stop).await)
+    }
+
+    async fn run(
+        simulation: Simulation,
+        render_count: i32,
+        species_count: usize,
+        generation: usize,
+        player: bool,
+        stop: Arc<AtomicBool>,
+    ) -> Arc<Mutex<Game>> {
         let game = {
-            let document = web_sys::window().unwrap().document().unwrap();
-            // Player Checkbox
-            let player_checkbox = get_html_input_element!(document, "player");
-            let player_checked = player_checkbox.checked();
-
-            // Speed Checkbox
-            let speed_checkbox = get_html_input_element!(document, "speed");
-            let speed_check = speed_checkbox.checked();
-            let canvas = document.get_element_by_id("canvas").unwrap();
-
-            let canvas: Arc<web_sys::HtmlCanvasElement> = Arc::new(
-                canvas
-                    .dyn_into::<web_sys::HtmlCanvasElement>()
-                    .map_err(|_| ())
-                    .unwrap(),
-            );
-            let context = Arc::new(Mutex::new(
-                canvas
-                    .get_context("2d")
-                    .unwrap()
-                    .unwrap()
-                    .dyn_into::<web_sys::CanvasRenderingContext2d>()
-                    .unwrap(),
-            ));
-            Arc::new(Mutex::new(Game::<GAME_TYPE>::new(
-                width,
-                height,
+            let context = Arc::new(Mutex::new(scaled_context(
+                "canvas",
+                simulation.width(),
+                simulation.height(),
+            )));
+            Arc::new(Mutex::new(Game::new(
+                simulation,
                 render_count,
                 species_count,
                 generation,
-                hole_size,
-                player_checked,
+                player,
+                stop,
                 context,
-                speed_check,
             )))
         };
         let game_cp = game.clone();
+        CURRENT_GAME.with(|current| *current.borrow_mut() = Some(game.clone()));
 
         let (sender, receiver) = oneshot::channel::<()>();
 
         {
             let sender = Arc::new(Mutex::new(Some(sender)));
-            {
-                game.lock().unwrap().init(networks);
-            }
+            #[allow(clippy::arc_with_non_send_sync)]
             let f = Arc::new(Mutex::new(None));
             let g = f.clone();
 
             *g.lock().unwrap() = Some(Closure::wrap(Box::new(move || {
                 let game_obj = &mut *game.lock().unwrap();
+                if game_obj.stop.load(Ordering::Relaxed) {
+                    // Dropping the handler unregisters the input listeners
+                    game_obj.player.take();
+                    game_obj.simulation.abort();
+                    let lock = sender.lock().unwrap().take();
+                    // Nobody waits for a game whose training was cancelled
+                    let _ = lock.unwrap().send(());
+                    return;
+                }
                 if !game_obj.started && !game_obj.check_started() {
                     game_obj.render_waiting();
                     request_animation_frame(f.lock().unwrap().as_ref().unwrap());
                     return;
                 }
-                game_obj.make_decisions();
-                game_obj.game_logic();
-                game_obj.handle_collisions();
-                game_obj.render();
+                // A human can't play faster than the display
+                let ticks = if controls::paused() {
+                    controls::take_steps()
+                } else if game_obj.player.is_some() {
+                    1
+                } else {
+                    controls::ticks_per_frame()
+                };
+                for _ in 0..ticks {
+                    game_obj.make_decisions();
+                    game_obj.game_logic();
+                    game_obj.handle_collisions();
+                    if game_obj.ended() {
+                        break;
+                    }
+                }
+                if controls::paused() {
+                    game_obj.render_paused();
+                } else if controls::rendering() {
+                    game_obj.render();
+                } else {
+                    game_obj.render_hud_only();
+                }
                 if !game_obj.ended() {
                     request_animation_frame(f.lock().unwrap().as_ref().unwrap());
                 } else {
                     let lock = sender.lock().unwrap().take();
-                    lock.unwrap().send(()).unwrap();
+                    // The receiver is gone when the training was cancelled meanwhile
+                    let _ = lock.unwrap().send(());
                 }
             }) as Box<dyn FnMut()>));
             {
@@ -301,225 +323,101 @@ impl<const GAME_TYPE: i32> Game<{ GAME_TYPE }> {
         started
     }
 
-    fn add_pipe(&mut self) {
-        let y = self.height
-            - self
-                .rng
-                .gen_range(self.height * 0.0..(self.height - self.hole_size));
-
-        match self.pipes.last() {
-            None => {
-                self.pipes.push(Pipe::new(
-                    self.width,
-                    0.25 * self.height + 0.5 * y,
-                    self.hole_size,
-                ));
-            }
-            Some(Pipe { x, .. }) => {
-                let x = *x;
-                self.pipes.push(Pipe::new(x + 500.0, y, self.hole_size));
-            }
-        }
-    }
-
-    fn get_speed(&self) -> f64 {
-        let mut result = 4.0;
-        result += self.get_speed_increase();
-        result
+    pub fn scores(&self) -> &[f64] {
+        &self.simulation.scores
     }
 
-    /// Returns the speed increase. 0.0 if speed increase is not activated
-    fn get_speed_increase(&self) -> f64 {
-        let mut result = 0.0;
-        if self.speed {
-            result += ((self.ticks as f64) * 0.002).tanh() * 2.5;
+    pub fn make_decisions(&mut self) {
+        self.simulation.make_decisions();
+        if let Some(player) = &self.player {
+            if player.is_pressed() {
+                self.simulation.player_jump();
+            }
         }
-        result
     }
 
-    /// Moves the pipes to the left
-    fn move_pipes(&mut self) {
-        let speed = self.get_speed();
-        for pipe in &mut self.pipes {
-            pipe.move_left(speed);
-        }
-        if self.pipes[0].x <= -pipe::WIDTH {
-            self.pipes.remove(0);
-            self.add_pipe();
-            self.current_score += 1.0;
+    pub fn handle_collisions(&mut self) {
+        self.simulation.handle_collisions();
+        // Dropping the handler unregisters the input listeners
+        if !self.simulation.has_player() {
+            self.player.take();
         }
     }
 
-    /// Applies "gravity" to every bird
-    fn apply_birds_velocity(&mut self) {
-        for bird in &mut self.birds {
-            bird.y_velocity();
-        }
-        if let Some(player) = &mut self.player {
-            player.bird.y_velocity();
-        }
+    pub fn game_logic(&mut self) {
+        self.simulation.game_logic();
     }
 
-    pub fn make_decisions(&mut self) {
-        let first_pipe = get_first_pipe!(self);
-
-        let mut inputs = [(first_pipe.x * 2.0 - self.width) / self.width, 0., 0.];
-
-        for bird in &mut self.birds {
-            inputs[1] = (bird.y - first_pipe.hole) / self.height;
-            inputs[2] = 0.01 * bird.velocity;
-            bird.make_decision(&inputs);
-        }
-        if let Some(player) = &mut self.player {
-            if player.is_pressed() {
-                player.bird.jump();
-            }
-        }
+    pub fn render_waiting(&self) {
+        let width = self.simulation.width();
+        let height = self.simulation.height();
+        let mut draw = DrawList::new();
+        draw.set_fill_style("rgba(50, 50, 50, 0.01)");
+        draw.rect(0.0, 0.0, width, height);
+        draw.fill();
+        draw.set_font("20px Arial");
+        draw.set_fill_style("white");
+        draw.fill_text(
+            "Press space or click to play",
+            width / 2.0 - 140.0,
+            height / 2.0 + 15.0,
+        );
+        draw.draw_on_canvas(&self.canvas_ctx.lock().unwrap());
     }
 
-    fn handle_pipe_collision(&mut self, index: usize) {
-        let pipe_ref = &self.pipes[index];
-        let overlap_x = pipe_ref.x <= bird::X + bird::RADIUS
-            && pipe_ref.x + pipe::WIDTH >= bird::X - bird::RADIUS;
-        let current_score = self.ticks as f64;
-
-        let scores = &mut self.scores;
-        let hole_size = self.hole_size;
-        if overlap_x {
-            self.birds.retain(|bird_ref| {
-                let alive = !(bird_ref.y + bird::RADIUS >= pipe_ref.y
-                    || bird_ref.y - bird::RADIUS <= pipe_ref.y - hole_size);
-                if !alive {
-                    scores[bird_ref.index] = current_score;
-                }
-                alive
-            });
-
-            if let Some(player) = &mut self.player {
-                let player_bird = &player.bird;
-                let alive = !(player_bird.y + bird::RADIUS >= pipe_ref.y
-                    || player_bird.y - bird::RADIUS <= pipe_ref.y - hole_size);
-                if !alive {
-                    self.player.take();
-                }
-            }
+    fn hud(&self) -> Hud {
+        Hud {
+            species_count: self.species_count,
+            generation: self.generation,
         }
     }
 
-    pub fn handle_collisions(&mut self) {
-        let height = self.height;
-        let current_score = self.ticks as f64;
-        self.ticks += 1;
-        let scores = &mut self.scores;
-
-        self.birds.retain(|bird_ref| {
-            let alive = bird_ref.y + bird::RADIUS <= height && bird_ref.y - bird::RADIUS >= 0.0;
-            if !alive {
-                scores[bird_ref.index] = current_score;
-            }
-            alive
-        });
-
-        if let Some(player) = &mut self.player {
-            let player_bird = &player.bird;
-            let alive =
-                player_bird.y + bird::RADIUS <= height && player_bird.y - bird::RADIUS >= 0.0;
-            if !alive {
-                self.player.take();
-            }
-        }
-
-        self.handle_pipe_collision(0);
-        self.handle_pipe_collision(1);
+    /// Game being played on the canvas, or the last one played
+    pub fn current() -> Option<Arc<Mutex<Game>>> {
+        CURRENT_GAME.with(|current| current.borrow().clone())
     }
 
-    pub fn game_logic(&mut self) {
-        self.move_pipes();
-        self.apply_birds_velocity();
+    /// SVG document of the current frame
+    pub fn snapshot_svg(&self) -> String {
+        svg::snapshot(&self.simulation, self.render_count as usize, &self.hud())
     }
 
-    fn random_color(&mut self) -> String {
-        // High value to not mix up with player
-        let c1 = self.rng.gen_range(100..255);
-        let c2 = self.rng.gen_range(100..255);
-        let c3 = self.rng.gen_range(100..255);
-
-        format!("rgb({}, {}, {})", c1, c2, c3)
+    /// Draw commands of the current frame
+    pub fn frame(&self) -> DrawList {
+        let mut frame = draw::draw_frame(&self.simulation, self.render_count as usize, &self.hud());
+        if controls::network_overlay() {
+            draw::draw_network_overlay(&self.simulation, &mut frame);
+        }
+        frame
     }
 
-    pub fn init(&mut self, nets: Vec<NeuralNetwork<f64>>) {
-        for _ in 0..5 {
-            self.add_pipe();
-        }
-        for (index, net) in nets.into_iter().enumerate() {
-            let random_color = self.random_color();
-            self.birds.push(Bird::new(index, random_color, net));
-            self.scores.push(0.0);
-        }
+    pub fn render(&self) {
+        self.frame()
+            .draw_on_canvas(&self.canvas_ctx.lock().unwrap());
     }
 
-    pub fn render_waiting(&self) {
-        let canvas_ctx = &*self.canvas_ctx.lock().unwrap();
-        canvas_ctx.set_fill_style(&JsValue::from_str("rgba(50, 50, 50, 0.01)"));
-        canvas_ctx.rect(0.0, 0.0, self.width, self.height);
-        canvas_ctx.fill();
-        canvas_ctx.set_font("20px Arial");
-        canvas_ctx.set_fill_style(&JsValue::from_str("white"));
-        canvas_ctx
-            .fill_text(
-                "Press space or click to play",
-                self.width / 2.0 - 140.0,
-                self.height / 2.0 + 15.0,
-            )
-            .unwrap();
+    /// Draws the whole frame whether rendering is enabled or not, so that it can be inspected
+    pub fn render_paused(&self) {
+        let mut frame = self.frame();
+        frame.set_font("20px Arial");
+        frame.set_fill_style("black");
+        frame.fill_text(
+            &format!("Paused at tick {}", self.simulation.ticks()),
+            self.simulation.width() - 220.0,
+            30.0,
+        );
+        frame.draw_on_canvas(&self.canvas_ctx.lock().unwrap());
     }
 
-    pub fn render(&self) {
-        let canvas_ctx = &*self.canvas_ctx.lock().unwrap();
-        canvas_ctx.clear_rect(0.0, 0.0, self.width, self.height);
-        for bird in self.birds.iter().take(self.render_count as usize) {
-            bird.render(canvas_ctx);
-        }
-        if let Some(player) = &self.player {
-            let player_bird = &player.bird;
-            player_bird.render(canvas_ctx);
-        }
-        for pipe in &self.pipes {
-            pipe.render(canvas_ctx);
-        }
-        canvas_ctx.set_font("30px Arial");
-        canvas_ctx.set_fill_style(&JsValue::from_str("black"));
-        canvas_ctx
-            .fill_text(
-                &*format!("{}", self.current_score),
-                self.width / 2.0 - 30.0,
-                30.0,
-            )
-            .unwrap();
-        canvas_ctx
-            .fill_text(
-                &*format!("Alive: {}", self.birds.len()),
-                self.width / 2.0 - 45.0,
-                self.height - 90.0,
-            )
-            .unwrap();
-        canvas_ctx
-            .fill_text(
-                &*format!("Species: {}", self.species_count),
-                self.width / 2.0 - 75.0,
-                self.height - 60.0,
-            )
-            .unwrap();
-        canvas_ctx
-            .fill_text(
-                &*format!("Generation: {}", self.generation),
-                self.width / 2.0 - 90.0,
-                self.height - 30.0,
-            )
-            .unwrap();
+    /// Only draws the counters, used when rendering the world is disabled
+    pub fn render_hud_only(&self) {
+        let mut draw = DrawList::new();
+        draw.clear_rect(0.0, 0.0, self.simulation.width(), self.simulation.height());
+        draw::draw_hud(&self.simulation, &self.hud(), &mut draw);
+        draw.draw_on_canvas(&self.canvas_ctx.lock().unwrap());
     }
 
     pub fn ended(&self) -> bool {
-        self.birds.is_empty() && self.player.is_none()
+        self.simulation.ended()
     }
 }
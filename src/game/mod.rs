@@ -1,3 +1,13 @@
 mod bird;
+pub mod draw;
+pub mod fitness;
+#[allow(clippy::module_inception)]
 pub mod game;
+pub mod mode;
+pub mod network;
+pub mod physics;
 mod pipe;
+pub mod replay;
+pub mod sensors;
+pub mod simulation;
+pub mod svg;
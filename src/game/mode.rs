@@ -0,0 +1,83 @@
+use crate::game::physics::Physics;
+use std::convert::TryFrom;
+
+/// Physics of the game, decides how a bird jumps and how networks control it.
+///
+/// Adding a mode only takes a new variant and its arms below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// Jumping resets the fall velocity
+    Classic,
+    /// Jumping only increments the current velocity, much harder to control
+    Aerodynamics,
+}
+
+pub const ALL_MODES: [GameMode; 2] = [GameMode::Classic, GameMode::Aerodynamics];
+
+impl GameMode {
+    /// Identifier used by `GameParams`, replays and saved champions
+    pub fn id(self) -> i32 {
+        match self {
+            GameMode::Classic => 0,
+            GameMode::Aerodynamics => 1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Aerodynamics => "aerodynamics",
+        }
+    }
+
+    /// Velocity of a bird right after it jumped
+    pub fn jump(self, velocity: f64, impulse: f64) -> f64 {
+        match self {
+            GameMode::Classic => impulse,
+            GameMode::Aerodynamics => velocity + impulse,
+        }
+    }
+
+    /// Physics preset giving the original behaviour of the mode
+    pub fn physics(self) -> Physics {
+        match self {
+            GameMode::Classic => Physics::classic(),
+            GameMode::Aerodynamics => Physics::aerodynamics(),
+        }
+    }
+
+    /// Number of outputs of the networks
+    pub fn outputs(self) -> usize {
+        match self {
+            GameMode::Classic => 1,
+            GameMode::Aerodynamics => 2,
+        }
+    }
+
+    /// Maps the outputs of a network to the decision of jumping
+    pub fn should_jump(self, outputs: &[f64]) -> bool {
+        match self {
+            GameMode::Classic | GameMode::Aerodynamics => outputs[0] >= 0.0,
+        }
+    }
+
+    /// Whether a human keeps jumping while the key is held, otherwise a press jumps once
+    pub fn repeats_held_input(self) -> bool {
+        match self {
+            GameMode::Classic => false,
+            GameMode::Aerodynamics => true,
+        }
+    }
+}
+
+impl TryFrom<i32> for GameMode {
+    type Error = String;
+
+    fn try_from(id: i32) -> Result<GameMode, String> {
+        ALL_MODES
+            .iter()
+            .cloned()
+            .find(|mode| mode.id() == id)
+            .ok_or(format!("Invalid game type: {}", id))
+    }
+}
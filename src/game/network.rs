@@ -0,0 +1,249 @@
+use crate::game::draw::DrawList;
+use serde::Deserialize;
+
+const NODE_RADIUS: f64 = 8.0;
+const PADDING: f64 = 20.0;
+
+/// Topology as serialized by `neat_gru`, with every field it requires when reading it back
+#[derive(Deserialize)]
+struct SerializedTopology {
+    biases: Vec<SerializedBias>,
+    genes: Vec<SerializedGene>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SerializedBias {
+    neuron: (u8, u8),
+    bias: SerializedBiasValues,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SerializedBiasValues {
+    bias_input: f64,
+    bias_update: f64,
+    bias_reset: f64,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SerializedGene {
+    connection_type: i32,
+    disabled: bool,
+    input: (u8, u8),
+    input_weight: f64,
+    memory_weight: f64,
+    output: (u8, u8),
+    reset_input_weight: f64,
+    reset_memory_weight: f64,
+    update_input_weight: f64,
+    update_memory_weight: f64,
+}
+
+/// Connection types `neat_gru` knows: sigmoid, GRU and ReLU
+const CONNECTION_TYPES: std::ops::RangeInclusive<i32> = 0..=2;
+
+/// Connection between two neurons, given as `(layer, index)`
+#[derive(Clone, Debug)]
+pub struct Connection {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub weight: f64,
+}
+
+/// Neurons and connections of a network, as drawn by the overlay
+#[derive(Clone, Debug)]
+pub struct NetworkLayout {
+    layers: Vec<usize>,
+    connections: Vec<Connection>,
+    /// Whether the hidden layers and the connections are known
+    complete: bool,
+}
+
+impl NetworkLayout {
+    /// Reads a topology serialized by `neat_gru`, disabled connections are left out.
+    ///
+    /// Fails on anything `neat_gru` would panic on or build a broken network from, so that
+    /// `NeuralNetwork::from_string` is safe to call on a topology accepted here
+    pub fn from_topology(topology: &serde_json::Value) -> Result<NetworkLayout, String> {
+        let topology = SerializedTopology::deserialize(topology).map_err(|err| err.to_string())?;
+        // `neat_gru` sizes the layers from the biases only
+        let mut layers = Vec::new();
+        for &(layer, index) in topology.biases.iter().map(|bias| &bias.neuron) {
+            let (layer, index) = (layer as usize, index as usize);
+            if layers.len() <= layer {
+                layers.resize(layer + 1, 0);
+            }
+            layers[layer] = layers[layer].max(index + 1);
+        }
+        let output_layer = match layers.len() {
+            0 | 1 => {
+                return Err(String::from(
+                    "A topology needs an input and an output layer",
+                ))
+            }
+            len => len - 1,
+        };
+        if layers[0] == 0 || layers[output_layer] == 0 {
+            return Err(String::from("The input and output layers can't be empty"));
+        }
+        let has_bias = |(layer, index): (u8, u8)| {
+            topology
+                .biases
+                .iter()
+                .any(|bias| bias.neuron == (layer, index))
+        };
+        let mut connections = Vec::new();
+        for gene in &topology.genes {
+            if !CONNECTION_TYPES.contains(&gene.connection_type) {
+                return Err(format!("Invalid connection type {}", gene.connection_type));
+            }
+            if gene.disabled {
+                continue;
+            }
+            let (from, to) = (
+                (gene.input.0 as usize, gene.input.1 as usize),
+                (gene.output.0 as usize, gene.output.1 as usize),
+            );
+            if from.0 >= output_layer || !has_bias(gene.input) {
+                return Err(format!("Connection from a missing neuron {:?}", from));
+            }
+            if to.0 <= from.0 || to.0 >= layers.len() || to.1 >= layers[to.0] {
+                return Err(format!("Connection to a missing neuron {:?}", to));
+            }
+            connections.push(Connection {
+                from,
+                to,
+                weight: gene.input_weight,
+            });
+        }
+        Ok(NetworkLayout {
+            layers,
+            connections,
+            complete: true,
+        })
+    }
+
+    /// Only the inputs and outputs, for networks whose topology isn't known.
+    ///
+    /// `neat_gru` doesn't give the topologies of a generation while it runs, only the networks
+    pub fn inputs_outputs(inputs: usize, outputs: usize) -> NetworkLayout {
+        NetworkLayout {
+            layers: vec![inputs, outputs],
+            connections: Vec::new(),
+            complete: false,
+        }
+    }
+
+    pub fn layers(&self) -> &[usize] {
+        &self.layers
+    }
+
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    pub fn complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Centre of a neuron in a panel of the given position and size
+    fn position(
+        &self,
+        (layer, index): (usize, usize),
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> (f64, f64) {
+        let columns = (self.layers.len().max(2) - 1) as f64;
+        let rows = self.layers[layer] as f64;
+        (
+            x + PADDING + (width - 2.0 * PADDING) * layer as f64 / columns,
+            y + PADDING + (height - 2.0 * PADDING) * (index as f64 + 0.5) / rows,
+        )
+    }
+
+    /// Draws the network in a panel, lighting the inputs and outputs up with their last values.
+    ///
+    /// Hidden neurons stay grey, their activations are private to `neat_gru`
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        inputs: &[f64],
+        outputs: &[f64],
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        draw: &mut DrawList,
+    ) {
+        draw.begin_path();
+        draw.set_fill_style("rgba(255, 255, 255, 0.85)");
+        draw.rect(x, y, width, height);
+        draw.fill();
+        draw.set_stroke_style("black");
+        draw.set_line_width(2.0);
+        draw.stroke();
+
+        for connection in &self.connections {
+            let (from_x, from_y) = self.position(connection.from, x, y, width, height);
+            let (to_x, to_y) = self.position(connection.to, x, y, width, height);
+            draw.begin_path();
+            draw.set_stroke_style(if connection.weight >= 0.0 {
+                "rgb(46, 134, 222)"
+            } else {
+                "rgb(235, 77, 75)"
+            });
+            draw.set_line_width(0.5 + connection.weight.abs().min(3.0));
+            draw.move_to(from_x, from_y);
+            draw.line_to(to_x, to_y);
+            draw.stroke();
+        }
+
+        let last = self.layers.len() - 1;
+        for (layer, &size) in self.layers.iter().enumerate() {
+            for index in 0..size {
+                let value = match layer {
+                    0 => inputs.get(index),
+                    _ if layer == last => outputs.get(index),
+                    _ => None,
+                };
+                let (node_x, node_y) = self.position((layer, index), x, y, width, height);
+                draw.begin_path();
+                draw.set_fill_style(&activation_color(value.cloned()));
+                draw.circle(node_x, node_y, NODE_RADIUS);
+                draw.fill();
+                draw.set_stroke_style("black");
+                draw.set_line_width(1.0);
+                draw.stroke();
+            }
+        }
+
+        if !self.complete {
+            draw.set_font("12px Arial");
+            draw.set_fill_style("black");
+            draw.fill_text(
+                "Hidden layers unknown while training",
+                x + PADDING,
+                y + height - 5.0,
+            );
+        }
+    }
+}
+
+/// Green for positive values, red for negative ones, grey when unknown
+fn activation_color(value: Option<f64>) -> String {
+    match value {
+        None => String::from("rgb(190, 190, 190)"),
+        Some(value) => {
+            let intensity = (value.abs().min(1.0) * 255.0) as u8;
+            if value >= 0.0 {
+                format!("rgb({}, 255, {})", 255 - intensity, 255 - intensity)
+            } else {
+                format!("rgb(255, {}, {})", 255 - intensity, 255 - intensity)
+            }
+        }
+    }
+}
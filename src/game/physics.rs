@@ -0,0 +1,68 @@
+use crate::game::pipe;
+use serde::{Deserialize, Serialize};
+
+/// Constants of the world, every mode has a preset giving its original behaviour
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Physics {
+    /// Velocity the birds lose every tick
+    pub gravity: f64,
+    /// Velocity right after a jump in classic mode, velocity added by a jump in aerodynamics
+    pub jump_impulse: f64,
+    /// Pixels the pipes move left every tick
+    pub pipe_speed: f64,
+    /// Horizontal distance between two consecutive pipes
+    pub pipe_spacing: f64,
+    /// Speed the accelerating pipes gain at most
+    pub speed_increase: f64,
+    /// How quickly the pipes accelerate, they gain `tanh(ticks * rate)` of `speed_increase`
+    pub speed_increase_rate: f64,
+}
+
+impl Physics {
+    pub fn classic() -> Physics {
+        Physics {
+            gravity: 0.5,
+            jump_impulse: 10.0,
+            pipe_speed: 4.0,
+            pipe_spacing: 500.0,
+            speed_increase: 2.5,
+            speed_increase_rate: 0.002,
+        }
+    }
+
+    pub fn aerodynamics() -> Physics {
+        Physics {
+            jump_impulse: 1.0,
+            ..Physics::classic()
+        }
+    }
+
+    /// Extra speed of the accelerating pipes after a given number of ticks
+    pub fn speed_increase_at(&self, ticks: usize) -> f64 {
+        (ticks as f64 * self.speed_increase_rate).tanh() * self.speed_increase
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.gravity.is_finite() || self.gravity < 0.0 {
+            return Err(String::from("The gravity can't be negative"));
+        }
+        if !self.jump_impulse.is_finite() || self.jump_impulse <= 0.0 {
+            return Err(String::from("The jump impulse must be positive"));
+        }
+        if !self.pipe_speed.is_finite() || self.pipe_speed <= 0.0 {
+            return Err(String::from("The pipe speed must be positive"));
+        }
+        if !self.pipe_spacing.is_finite()
+            || self.pipe_spacing <= pipe::WIDTH + 2.0 * pipe::LIP_OVERHANG
+        {
+            return Err(String::from("The pipes must be spaced wider than a pipe"));
+        }
+        if !self.speed_increase.is_finite() || self.speed_increase < 0.0 {
+            return Err(String::from("The speed increase can't be negative"));
+        }
+        if !self.speed_increase_rate.is_finite() || self.speed_increase_rate < 0.0 {
+            return Err(String::from("The speed increase rate can't be negative"));
+        }
+        Ok(())
+    }
+}
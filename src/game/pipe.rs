@@ -1,57 +1,218 @@
-use crate::game::game::Render;
-use wasm_bindgen::JsValue;
+use crate::game::draw::{DrawList, Render};
+use std::f64::consts::PI;
 
 pub const WIDTH: f64 = 100.0;
 pub const BORDER_WIDTH: f64 = 20.0;
 pub const LINE_WIDTH: f64 = 5.0;
+/// How far the lips stick out of the body on each side
+pub const LIP_OVERHANG: f64 = 5.0;
+/// Largest distance between two positions checked by `swept_collides`, well under the lips'
+/// `BORDER_WIDTH` so that nothing can be skipped over
+const MAX_SUBSTEP: f64 = 2.0;
+
+/// Whether a circle overlaps the rectangle going from `(left, top)` to `(right, bottom)`
+fn circle_hits_rect(
+    (x, y, radius): (f64, f64, f64),
+    (left, top, right, bottom): (f64, f64, f64, f64),
+) -> bool {
+    let closest_x = x.max(left).min(right);
+    let closest_y = y.max(top).min(bottom);
+    (x - closest_x).powi(2) + (y - closest_y).powi(2) <= radius * radius
+}
 
 pub struct Pipe {
     pub x: f64,
     pub y: f64,
     pub hole: f64,
     hole_size: f64,
+    /// `y` the gap oscillates around when the pipes move
+    base_y: f64,
+    /// Offset of the oscillation so the pipes don't all move together
+    phase: f64,
+    /// How far the pipe moved during the last tick
+    moved: (f64, f64),
+    /// Height of the world, the pipe reaches its top and bottom edges
+    world_height: f64,
 }
 
 impl Pipe {
-    pub fn new(x: f64, y: f64, hole_size: f64) -> Pipe {
+    pub fn new(x: f64, y: f64, hole_size: f64, phase: f64, world_height: f64) -> Pipe {
         Pipe {
             x,
             y,
             hole_size,
             hole: y - hole_size / 2.,
+            base_y: y,
+            phase,
+            moved: (0.0, 0.0),
+            world_height,
         }
     }
 
+    /// Lowest `y` of the hole
+    pub fn gap_top(&self) -> f64 {
+        self.y - self.hole_size
+    }
+
+    /// Highest `y` of the hole
+    pub fn gap_bottom(&self) -> f64 {
+        self.y
+    }
+
+    /// Whether a circle overlaps the bodies or the lips of the pipe, as `render` draws them
+    pub fn collides(&self, x: f64, y: f64, radius: f64) -> bool {
+        let circle = (x, y, radius);
+        let (left, right) = (self.x, self.x + WIDTH);
+        let (lip_left, lip_right) = (left - LIP_OVERHANG, right + LIP_OVERHANG);
+        let (top, bottom) = (self.gap_top(), self.gap_bottom());
+        // The bodies run past the edges of the world
+        circle_hits_rect(circle, (left, f64::NEG_INFINITY, right, top - BORDER_WIDTH))
+            || circle_hits_rect(circle, (lip_left, top - BORDER_WIDTH, lip_right, top))
+            || circle_hits_rect(circle, (lip_left, bottom, lip_right, bottom + BORDER_WIDTH))
+            || circle_hits_rect(circle, (left, bottom + BORDER_WIDTH, right, f64::INFINITY))
+    }
+
+    /// Whether a circle going from `from` to `to` during the last tick hit the pipe at any
+    /// point, the motion of the pipe included.
+    ///
+    /// The path is checked every `MAX_SUBSTEP` pixels, relatively to the pipe, so that fast
+    /// birds and pipes can't go through each other between two ticks
+    pub fn swept_collides(&self, from: (f64, f64), to: (f64, f64), radius: f64) -> bool {
+        // Where the circle started relatively to the pipe's current position
+        let start = (from.0 + self.moved.0, from.1 + self.moved.1);
+        let (dx, dy) = (to.0 - start.0, to.1 - start.1);
+        let substeps = ((dx.hypot(dy) / MAX_SUBSTEP).ceil() as usize).max(1);
+        (1..=substeps).any(|substep| {
+            let progress = substep as f64 / substeps as f64;
+            self.collides(start.0 + dx * progress, start.1 + dy * progress, radius)
+        })
+    }
+
     /// Moves the pipe left at a given speed
     pub fn move_left(&mut self, speed: f64) {
         self.x -= speed;
+        self.moved = (-speed, 0.0);
+    }
+
+    /// Moves the gap up and down, `frequency` being in oscillations per 100 ticks.
+    /// The gap never leaves the world
+    pub fn oscillate(&mut self, ticks: usize, amplitude: f64, frequency: f64) {
+        let angle = ticks as f64 * frequency * 2.0 * PI / 100.0 + self.phase;
+        let previous_y = self.y;
+        self.y = (self.base_y + amplitude * angle.sin())
+            .max(self.hole_size)
+            .min(self.world_height);
+        self.hole = self.y - self.hole_size / 2.;
+        self.moved.1 += self.y - previous_y;
     }
 }
 
 impl Render for Pipe {
-    fn render(&self, canvas_ctx: &web_sys::CanvasRenderingContext2d) {
+    fn render(&self, draw: &mut DrawList) {
         let h_size = self.hole_size;
-        let height = 800.0 - h_size;
-        canvas_ctx.begin_path();
-        canvas_ctx.set_fill_style(&JsValue::from_str("#6ebb2d"));
-        canvas_ctx.set_stroke_style(&JsValue::from_str("black"));
-        canvas_ctx.set_line_width(LINE_WIDTH);
-        canvas_ctx.rect(self.x, self.y + BORDER_WIDTH, WIDTH, height);
-        canvas_ctx.fill();
-        canvas_ctx.rect(self.x, self.y + BORDER_WIDTH, WIDTH, height);
-        canvas_ctx.stroke();
-        canvas_ctx.rect(self.x - 5.0, self.y, WIDTH + 10.0, BORDER_WIDTH);
-        canvas_ctx.fill();
-        canvas_ctx.rect(self.x - 5.0, self.y, WIDTH + 10.0, BORDER_WIDTH);
-        canvas_ctx.stroke();
-
-        canvas_ctx.rect(self.x, self.y - h_size - BORDER_WIDTH, WIDTH, -height);
-        canvas_ctx.fill();
-        canvas_ctx.rect(self.x, self.y - h_size - BORDER_WIDTH, WIDTH, -height);
-        canvas_ctx.stroke();
-        canvas_ctx.rect(self.x - 5.0, self.y - h_size, WIDTH + 10.0, -BORDER_WIDTH);
-        canvas_ctx.fill();
-        canvas_ctx.rect(self.x - 5.0, self.y - h_size, WIDTH + 10.0, -BORDER_WIDTH);
-        canvas_ctx.stroke();
+        let height = self.world_height - h_size;
+        draw.begin_path();
+        draw.set_fill_style("#6ebb2d");
+        draw.set_stroke_style("black");
+        draw.set_line_width(LINE_WIDTH);
+        draw.rect(self.x, self.y + BORDER_WIDTH, WIDTH, height);
+        draw.fill();
+        draw.rect(self.x, self.y + BORDER_WIDTH, WIDTH, height);
+        draw.stroke();
+        draw.rect(
+            self.x - LIP_OVERHANG,
+            self.y,
+            WIDTH + 2.0 * LIP_OVERHANG,
+            BORDER_WIDTH,
+        );
+        draw.fill();
+        draw.rect(
+            self.x - LIP_OVERHANG,
+            self.y,
+            WIDTH + 2.0 * LIP_OVERHANG,
+            BORDER_WIDTH,
+        );
+        draw.stroke();
+
+        draw.rect(self.x, self.y - h_size - BORDER_WIDTH, WIDTH, -height);
+        draw.fill();
+        draw.rect(self.x, self.y - h_size - BORDER_WIDTH, WIDTH, -height);
+        draw.stroke();
+        draw.rect(
+            self.x - LIP_OVERHANG,
+            self.y - h_size,
+            WIDTH + 2.0 * LIP_OVERHANG,
+            -BORDER_WIDTH,
+        );
+        draw.fill();
+        draw.rect(
+            self.x - LIP_OVERHANG,
+            self.y - h_size,
+            WIDTH + 2.0 * LIP_OVERHANG,
+            -BORDER_WIDTH,
+        );
+        draw.stroke();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gap from 300 to 500, lips from 95 to 205 on the x axis
+    fn pipe() -> Pipe {
+        Pipe::new(100.0, 500.0, 200.0, 0.0, 800.0)
+    }
+
+    #[test]
+    fn circle_touching_a_lip_corner_collides() {
+        let pipe = pipe();
+        // 3-4-5 away from the top left corner of the bottom lip
+        assert!(pipe.collides(92.0, 496.0, 5.0));
+        assert!(!pipe.collides(92.0, 496.0, 4.9));
+    }
+
+    #[test]
+    fn circle_inside_the_gap_does_not_collide() {
+        let pipe = pipe();
+        assert!(!pipe.collides(150.0, 400.0, 10.0));
+        assert!(!pipe.collides(150.0, 311.0, 10.0));
+        assert!(!pipe.collides(150.0, 489.0, 10.0));
+    }
+
+    #[test]
+    fn lip_overhang_collides_but_not_under_it() {
+        let pipe = pipe();
+        assert!(pipe.collides(97.0, 510.0, 1.0));
+        assert!(pipe.collides(203.0, 290.0, 1.0));
+        // Beside the body, under the lip
+        assert!(!pipe.collides(97.0, 530.0, 1.0));
+        assert!(!pipe.collides(203.0, 270.0, 1.0));
+    }
+
+    #[test]
+    fn fast_fall_through_a_lip_collides() {
+        let pipe = pipe();
+        let (from, to) = ((97.0, 490.0), (97.0, 530.0));
+        assert!(!pipe.collides(from.0, from.1, 2.0));
+        assert!(!pipe.collides(to.0, to.1, 2.0));
+        assert!(pipe.swept_collides(from, to, 2.0));
+    }
+
+    #[test]
+    fn fast_pipe_through_a_still_circle_collides() {
+        let mut pipe = Pipe::new(240.0, 500.0, 200.0, 0.0, 800.0);
+        pipe.move_left(140.0);
+        // The lip went from one side of the circle to the other within the tick
+        assert!(!pipe.collides(220.0, 510.0, 2.0));
+        assert!(!pipe.collides(80.0, 510.0, 2.0));
+        assert!(pipe.swept_collides((220.0, 510.0), (220.0, 510.0), 2.0));
+    }
+
+    #[test]
+    fn swept_path_inside_the_gap_does_not_collide() {
+        let mut pipe = pipe();
+        pipe.move_left(40.0);
+        assert!(!pipe.swept_collides((60.0, 400.0), (150.0, 420.0), 10.0));
     }
 }
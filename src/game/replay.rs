@@ -0,0 +1,78 @@
+use crate::game::fitness::Fitness;
+use crate::game::mode::GameMode;
+use crate::game::physics::Physics;
+use crate::game::simulation;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Bumped whenever the format changes in a way older readers can't handle, or the physics
+/// change so that older replays wouldn't play back the same
+pub const REPLAY_VERSION: u32 = 3;
+
+/// Everything needed to play a game back frame-exact without the neural networks
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub game_type: i32,
+    pub seed: u64,
+    pub width: f64,
+    pub height: f64,
+    pub hole_size: i32,
+    pub speed: bool,
+    /// Id of the `Fitness` the scores were computed with
+    pub fitness: i32,
+    /// Oscillation of the gaps, 0 when the pipes don't move
+    pub pipe_amplitude: f64,
+    pub pipe_frequency: f64,
+    /// Limits that ended the game early, 0 for no limit
+    pub max_ticks: usize,
+    pub max_pipes: usize,
+    /// Physics the game was played with, the preset of the mode when absent
+    #[serde(default)]
+    pub physics: Option<Physics>,
+    /// `y` of every pipe, in the order they were added
+    pub pipes: Vec<f64>,
+    pub birds: Vec<ReplayBird>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayBird {
+    pub color: String,
+    /// Ticks at which the bird flapped, in increasing order
+    pub flaps: Vec<usize>,
+    /// Score obtained when recording, playing back must give the same
+    pub score: f64,
+}
+
+impl Replay {
+    pub fn from_json(serialized: &str) -> Result<Replay, String> {
+        let replay: Replay = serde_json::from_str(serialized).map_err(|err| err.to_string())?;
+        if replay.version != REPLAY_VERSION {
+            return Err(format!(
+                "Unsupported replay version {}, expected {}",
+                replay.version, REPLAY_VERSION
+            ));
+        }
+        GameMode::try_from(replay.game_type)?;
+        Fitness::try_from(replay.fitness)?;
+        simulation::validate_world(replay.width, replay.height, replay.hole_size)?;
+        if let Some(physics) = &replay.physics {
+            physics.validate()?;
+        }
+        Ok(replay)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Keeps a single bird of the recording
+    pub fn bird(&self, index: usize) -> Option<Replay> {
+        let bird = self.birds.get(index)?.clone();
+        Some(Replay {
+            birds: vec![bird],
+            pipes: self.pipes.clone(),
+            ..*self
+        })
+    }
+}
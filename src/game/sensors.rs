@@ -0,0 +1,168 @@
+use crate::game::pipe::Pipe;
+
+/// A single input fed to the neural networks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sensor {
+    /// Horizontal position of the next pipe
+    PipeX,
+    /// Vertical offset between the bird and the centre of the next hole
+    HoleOffset,
+    Velocity,
+    /// Horizontal position of the pipe after the next one
+    SecondPipeX,
+    /// Vertical offset between the bird and the centre of the hole after the next one
+    SecondHoleOffset,
+    /// Distance to the top edge of the next hole
+    GapTop,
+    /// Distance to the bottom edge of the next hole
+    GapBottom,
+    Ceiling,
+    Floor,
+    PipeSpeed,
+    TicksSinceFlap,
+}
+
+/// Every sensor, in the order their inputs are given to the networks
+pub const ALL_SENSORS: [Sensor; 11] = [
+    Sensor::PipeX,
+    Sensor::HoleOffset,
+    Sensor::Velocity,
+    Sensor::SecondPipeX,
+    Sensor::SecondHoleOffset,
+    Sensor::GapTop,
+    Sensor::GapBottom,
+    Sensor::Ceiling,
+    Sensor::Floor,
+    Sensor::PipeSpeed,
+    Sensor::TicksSinceFlap,
+];
+
+impl Sensor {
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sensor::PipeX => "pipe_x",
+            Sensor::HoleOffset => "hole_offset",
+            Sensor::Velocity => "velocity",
+            Sensor::SecondPipeX => "second_pipe_x",
+            Sensor::SecondHoleOffset => "second_hole_offset",
+            Sensor::GapTop => "gap_top",
+            Sensor::GapBottom => "gap_bottom",
+            Sensor::Ceiling => "ceiling",
+            Sensor::Floor => "floor",
+            Sensor::PipeSpeed => "pipe_speed",
+            Sensor::TicksSinceFlap => "ticks_since_flap",
+        }
+    }
+}
+
+/// What a bird can see of the world at a given tick
+pub struct Surroundings<'a> {
+    pub first_pipe: &'a Pipe,
+    pub second_pipe: &'a Pipe,
+    pub width: f64,
+    pub height: f64,
+    pub speed: f64,
+}
+
+/// State of the bird reading the sensors
+pub struct BirdState {
+    pub y: f64,
+    pub velocity: f64,
+    pub ticks_since_flap: usize,
+}
+
+/// Set of sensors selected for a training, stored as a bit mask so it fits in `GameParams`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sensors(u32);
+
+impl Default for Sensors {
+    /// The inputs the game always had: pipe x, offset from the hole and velocity
+    fn default() -> Sensors {
+        Sensors::from_sensors(&[Sensor::PipeX, Sensor::HoleOffset, Sensor::Velocity])
+    }
+}
+
+impl Sensors {
+    pub fn from_sensors(sensors: &[Sensor]) -> Sensors {
+        Sensors(sensors.iter().fold(0, |mask, sensor| mask | sensor.bit()))
+    }
+
+    pub fn from_mask(mask: u32) -> Result<Sensors, String> {
+        let all = Sensors::from_sensors(&ALL_SENSORS);
+        if mask == 0 {
+            return Err(String::from("At least one sensor must be selected"));
+        }
+        if mask & !all.0 != 0 {
+            return Err(format!("Invalid sensors mask: {}", mask));
+        }
+        Ok(Sensors(mask))
+    }
+
+    /// Parses a comma separated list of sensor names
+    pub fn from_names(names: &str) -> Result<Sensors, String> {
+        let sensors = names
+            .split(',')
+            .map(|name| {
+                ALL_SENSORS
+                    .iter()
+                    .find(|sensor| sensor.name() == name.trim())
+                    .cloned()
+                    .ok_or(format!("Unknown sensor: {}", name))
+            })
+            .collect::<Result<Vec<Sensor>, String>>()?;
+        Sensors::from_mask(Sensors::from_sensors(&sensors).0)
+    }
+
+    pub fn mask(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, sensor: Sensor) -> bool {
+        self.0 & sensor.bit() != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Sensor> {
+        ALL_SENSORS
+            .iter()
+            .cloned()
+            .filter(move |sensor| self.contains(*sensor))
+    }
+
+    /// Number of inputs of the networks
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Fills `inputs` with the value of every selected sensor
+    pub fn observe(self, surroundings: &Surroundings, bird: &BirdState, inputs: &mut Vec<f64>) {
+        let Surroundings {
+            first_pipe,
+            second_pipe,
+            width,
+            height,
+            speed,
+        } = *surroundings;
+        inputs.clear();
+        inputs.extend(self.iter().map(|sensor| match sensor {
+            Sensor::PipeX => (first_pipe.x * 2.0 - width) / width,
+            Sensor::HoleOffset => (bird.y - first_pipe.hole) / height,
+            Sensor::Velocity => 0.01 * bird.velocity,
+            Sensor::SecondPipeX => (second_pipe.x * 2.0 - width) / width,
+            Sensor::SecondHoleOffset => (bird.y - second_pipe.hole) / height,
+            Sensor::GapTop => (bird.y - first_pipe.gap_top()) / height,
+            Sensor::GapBottom => (first_pipe.gap_bottom() - bird.y) / height,
+            Sensor::Ceiling => bird.y / height,
+            Sensor::Floor => (height - bird.y) / height,
+            Sensor::PipeSpeed => 0.1 * speed,
+            Sensor::TicksSinceFlap => 0.01 * bird.ticks_since_flap as f64,
+        }));
+    }
+}
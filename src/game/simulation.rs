@@ -0,0 +1,728 @@
+use crate::game::bird::Bird;
+use crate::game::fitness::{Death, Fitness, FitnessFn};
+use crate::game::mode::GameMode;
+use crate::game::network::NetworkLayout;
+use crate::game::physics::Physics;
+use crate::game::pipe::Pipe;
+use crate::game::replay::{Replay, ReplayBird, REPLAY_VERSION};
+use crate::game::sensors::{BirdState, Sensors, Surroundings};
+use crate::game::{bird, pipe};
+use neat_gru::neural_network::NeuralNetwork;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// What happened during a game, turned into a `Replay` at the end
+struct Recording {
+    pipes: Vec<f64>,
+    colors: Vec<String>,
+    flaps: Vec<Vec<usize>>,
+}
+
+/// Headless Flappy Bird world: pipes, birds, physics and collisions.
+///
+/// Holds no rendering or DOM state so it can run anywhere, the canvas `Game` is a thin layer
+/// on top of it.
+pub struct Simulation {
+    mode: GameMode,
+    pipes: Vec<Pipe>,
+    birds: Vec<Bird>,
+    pub scores: Vec<f64>,
+    player: Option<Bird>,
+    rng: StdRng,
+    seed: u64,
+    sensors: Sensors,
+    /// Built-in fitness of `fitness_fn`, `None` for a custom one
+    fitness: Option<Fitness>,
+    fitness_fn: Arc<dyn FitnessFn>,
+    physics: Physics,
+    recording: Option<Recording>,
+    /// Recorded pipes and flaps when playing a replay back
+    pipes_script: VecDeque<f64>,
+    flaps_script: Option<Vec<VecDeque<usize>>>,
+    width: f64,
+    height: f64,
+    hole_size: f64,
+    ticks: usize,
+    current_score: f64,
+    speed: bool,
+    /// Pipes added since the start, gives every pipe its own oscillation phase
+    pipes_added: usize,
+    pipe_amplitude: f64,
+    pipe_frequency: f64,
+    /// Limits ending the game with birds still alive, 0 for no limit
+    max_ticks: usize,
+    max_pipes: usize,
+    capped: bool,
+}
+
+/// Checks the size of a world and of its holes, `Simulation::new` expects them valid
+pub fn validate_world(width: f64, height: f64, hole_size: i32) -> Result<(), String> {
+    if hole_size < 1 {
+        return Err(String::from("The hole size must be positive"));
+    }
+    if !width.is_finite() || width <= 0.0 {
+        return Err(String::from("The world width must be positive"));
+    }
+    if !height.is_finite() || height <= hole_size as f64 {
+        return Err(String::from("The world must be higher than the hole"));
+    }
+    Ok(())
+}
+
+impl Simulation {
+    /// Same seed and same networks always give the same scores
+    pub fn new(
+        mode: GameMode,
+        width: f64,
+        height: f64,
+        hole_size: i32,
+        speed: bool,
+        seed: u64,
+    ) -> Simulation {
+        let rng = StdRng::seed_from_u64(seed);
+        Simulation {
+            mode,
+            width,
+            height,
+            rng,
+            seed,
+            sensors: Sensors::default(),
+            fitness: Some(Fitness::default()),
+            fitness_fn: Fitness::default().function(),
+            physics: mode.physics(),
+            recording: None,
+            pipes_script: VecDeque::new(),
+            flaps_script: None,
+            hole_size: hole_size as f64,
+            player: None,
+            pipes: Vec::new(),
+            birds: Vec::new(),
+            scores: Vec::new(),
+            current_score: 0.0,
+            ticks: 0,
+            speed,
+            pipes_added: 0,
+            pipe_amplitude: 0.0,
+            pipe_frequency: 0.0,
+            max_ticks: 0,
+            max_pipes: 0,
+            capped: false,
+        }
+    }
+
+    /// Creates a simulation replaying the recorded pipes and flaps instead of neural networks
+    pub fn from_replay(replay: &Replay) -> Result<Simulation, String> {
+        let mut simulation = Simulation::new(
+            GameMode::try_from(replay.game_type)?,
+            replay.width,
+            replay.height,
+            replay.hole_size,
+            replay.speed,
+            replay.seed,
+        );
+        simulation.set_fitness(Fitness::try_from(replay.fitness)?);
+        if let Some(physics) = replay.physics {
+            simulation.physics = physics;
+        }
+        simulation.set_moving_pipes(replay.pipe_amplitude, replay.pipe_frequency);
+        simulation.set_limits(replay.max_ticks, replay.max_pipes);
+        simulation.pipes_script = replay.pipes.iter().cloned().collect();
+        simulation.flaps_script = Some(
+            replay
+                .birds
+                .iter()
+                .map(|bird| bird.flaps.iter().cloned().collect())
+                .collect(),
+        );
+        simulation.add_pipes();
+        for (index, bird) in replay.birds.iter().enumerate() {
+            simulation.birds.push(Bird::new_without_handler(
+                index,
+                simulation.mode,
+                simulation.height / 2.0,
+                bird.color.clone(),
+            ));
+            simulation.scores.push(0.0);
+        }
+        Ok(simulation)
+    }
+
+    pub fn init(&mut self, nets: Vec<NeuralNetwork<f64>>) {
+        self.add_pipes();
+        for (index, net) in nets.into_iter().enumerate() {
+            let random_color = self.random_color();
+            if let Some(recording) = &mut self.recording {
+                recording.colors.push(random_color.clone());
+                recording.flaps.push(Vec::new());
+            }
+            self.birds.push(Bird::new(
+                index,
+                self.mode,
+                self.height / 2.0,
+                random_color,
+                net,
+            ));
+            self.scores.push(0.0);
+        }
+    }
+
+    /// Gives the birds the structure of their networks, must be called after `init`
+    pub fn set_layouts(&mut self, layouts: Vec<NetworkLayout>) {
+        for (bird, layout) in self.birds.iter_mut().zip(layouts) {
+            bird.set_layout(layout);
+        }
+    }
+
+    /// Records the pipes and every decision, must be called before `init`
+    pub fn record(&mut self) {
+        self.recording = Some(Recording {
+            pipes: Vec::new(),
+            colors: Vec::new(),
+            flaps: Vec::new(),
+        });
+    }
+
+    /// Returns the recorded game, `None` if `record` wasn't called or the birds are scored by a
+    /// custom fitness, that a replay couldn't score again
+    pub fn replay(&self) -> Option<Replay> {
+        let recording = self.recording.as_ref()?;
+        let fitness = self.fitness?;
+        let birds = recording
+            .colors
+            .iter()
+            .zip(recording.flaps.iter())
+            .zip(self.scores.iter())
+            .map(|((color, flaps), &score)| ReplayBird {
+                color: color.clone(),
+                flaps: flaps.clone(),
+                score,
+            })
+            .collect();
+        Some(Replay {
+            version: REPLAY_VERSION,
+            game_type: self.mode.id(),
+            seed: self.seed,
+            width: self.width,
+            height: self.height,
+            hole_size: self.hole_size as i32,
+            speed: self.speed,
+            fitness: fitness.id(),
+            pipe_amplitude: self.pipe_amplitude,
+            pipe_frequency: self.pipe_frequency,
+            max_ticks: self.max_ticks,
+            max_pipes: self.max_pipes,
+            physics: Some(self.physics),
+            pipes: recording.pipes.clone(),
+            birds,
+        })
+    }
+
+    /// Adds a bird controlled from outside of the simulation through `player_jump`
+    pub fn add_player(&mut self) {
+        self.player = Some(Bird::new_without_handler(
+            bird::PLAYER_INDEX,
+            self.mode,
+            self.height / 2.0,
+            String::from("black"),
+        ));
+    }
+
+    /// Makes the player's bird jump, does nothing if it is dead or absent
+    pub fn player_jump(&mut self) {
+        if let Some(player) = &mut self.player {
+            player.jump(&self.physics);
+        }
+    }
+
+    /// Selects the inputs given to the neural networks
+    pub fn set_sensors(&mut self, sensors: Sensors) {
+        self.sensors = sensors;
+    }
+
+    /// Selects how the death of a bird is turned into its score
+    pub fn set_fitness(&mut self, fitness: Fitness) {
+        self.fitness = Some(fitness);
+        self.fitness_fn = fitness.function();
+    }
+
+    /// Scores the birds with a fitness that isn't built in
+    pub fn set_custom_fitness(&mut self, fitness: impl FitnessFn + 'static) {
+        self.fitness = None;
+        self.fitness_fn = Arc::new(fitness);
+    }
+
+    /// Replaces the physics preset of the mode
+    pub fn set_physics(&mut self, physics: Physics) {
+        self.physics = physics;
+    }
+
+    pub fn physics(&self) -> &Physics {
+        &self.physics
+    }
+
+    /// Makes the gaps oscillate up and down, `frequency` being in oscillations per 100 ticks.
+    /// An amplitude of 0 keeps the pipes still
+    pub fn set_moving_pipes(&mut self, amplitude: f64, frequency: f64) {
+        self.pipe_amplitude = amplitude;
+        self.pipe_frequency = frequency;
+    }
+
+    /// Ends the game once `max_ticks` ticks or `max_pipes` pipes are reached, the birds still
+    /// alive get their score then. 0 means no limit
+    pub fn set_limits(&mut self, max_ticks: usize, max_pipes: usize) {
+        self.max_ticks = max_ticks;
+        self.max_pipes = max_pipes;
+    }
+
+    /// Whether the game was ended by `set_limits` rather than by the death of every bird
+    pub fn capped(&self) -> bool {
+        self.capped
+    }
+
+    /// Makes the pipes accelerate over time
+    pub fn set_speed(&mut self, speed: bool) {
+        self.speed = speed;
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn has_player(&self) -> bool {
+        self.player.is_some()
+    }
+
+    pub fn birds(&self) -> &[Bird] {
+        &self.birds
+    }
+
+    /// Bird shown by the network overlay: the first one alive, which for champions is the best
+    /// one still alive
+    pub fn leader(&self) -> Option<&Bird> {
+        self.birds.first()
+    }
+
+    pub fn player(&self) -> Option<&Bird> {
+        self.player.as_ref()
+    }
+
+    pub fn pipes(&self) -> &[Pipe] {
+        &self.pipes
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    pub fn hole_size(&self) -> i32 {
+        self.hole_size as i32
+    }
+
+    pub fn current_score(&self) -> f64 {
+        self.current_score
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    fn add_pipes(&mut self) {
+        for _ in 0..5 {
+            self.add_pipe();
+        }
+    }
+
+    /// Height of the next pipe, taken from the replay when there is one
+    fn next_pipe_y(&mut self) -> f64 {
+        if let Some(y) = self.pipes_script.pop_front() {
+            return y;
+        }
+        let y = self.height
+            - self
+                .rng
+                .gen_range(self.height * 0.0..(self.height - self.hole_size));
+        if self.pipes.is_empty() {
+            0.25 * self.height + 0.5 * y
+        } else {
+            y
+        }
+    }
+
+    fn add_pipe(&mut self) {
+        let y = self.next_pipe_y();
+        if let Some(recording) = &mut self.recording {
+            recording.pipes.push(y);
+        }
+
+        let x = match self.pipes.last() {
+            None => self.width,
+            Some(Pipe { x, .. }) => *x + self.physics.pipe_spacing,
+        };
+        let phase = self.pipes_added as f64;
+        self.pipes_added += 1;
+        self.pipes
+            .push(Pipe::new(x, y, self.hole_size, phase, self.height));
+    }
+
+    fn get_speed(&self) -> f64 {
+        let mut result = self.physics.pipe_speed;
+        result += self.get_speed_increase();
+        result
+    }
+
+    /// Returns the speed increase. 0.0 if speed increase is not activated
+    fn get_speed_increase(&self) -> f64 {
+        let mut result = 0.0;
+        if self.speed {
+            result += self.physics.speed_increase_at(self.ticks);
+        }
+        result
+    }
+
+    /// Moves the pipes to the left
+    fn move_pipes(&mut self) {
+        let speed = self.get_speed();
+        for pipe in &mut self.pipes {
+            pipe.move_left(speed);
+            if self.pipe_amplitude > 0.0 {
+                pipe.oscillate(self.ticks, self.pipe_amplitude, self.pipe_frequency);
+            }
+        }
+        if self.pipes[0].x <= -pipe::WIDTH {
+            self.pipes.remove(0);
+            self.add_pipe();
+            self.current_score += 1.0;
+        }
+    }
+
+    /// Applies "gravity" to every bird
+    fn apply_birds_velocity(&mut self) {
+        for bird in &mut self.birds {
+            bird.y_velocity(&self.physics);
+        }
+        if let Some(player) = &mut self.player {
+            player.y_velocity(&self.physics);
+        }
+    }
+
+    /// Index of the next pipe the birds have to go through
+    fn first_pipe_index(&self) -> usize {
+        if self.pipes[0].x + pipe::WIDTH + pipe::LIP_OVERHANG >= bird::X - bird::RADIUS {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Lets every neural network decide whether its bird jumps, or follows the replay
+    pub fn make_decisions(&mut self) {
+        let first_pipe = self.first_pipe_index();
+        let surroundings = Surroundings {
+            first_pipe: &self.pipes[first_pipe],
+            second_pipe: &self.pipes[first_pipe + 1],
+            width: self.width,
+            height: self.height,
+            speed: self.get_speed(),
+        };
+        let tick = self.ticks;
+
+        let mut inputs = Vec::with_capacity(self.sensors.len());
+
+        for bird in &mut self.birds {
+            let jumped = match &mut self.flaps_script {
+                Some(script) => {
+                    let flaps = &mut script[bird.index];
+                    let flap = flaps.front() == Some(&tick);
+                    if flap {
+                        flaps.pop_front();
+                        bird.jump(&self.physics);
+                    }
+                    flap
+                }
+                None => {
+                    let state = BirdState {
+                        y: bird.y,
+                        velocity: bird.velocity,
+                        ticks_since_flap: bird.ticks_since_flap,
+                    };
+                    self.sensors.observe(&surroundings, &state, &mut inputs);
+                    bird.make_decision(&inputs, &self.physics)
+                }
+            };
+            if let (true, Some(recording)) = (jumped, &mut self.recording) {
+                recording.flaps[bird.index].push(tick);
+            }
+        }
+    }
+
+    /// Pipes entirely behind the birds, including the ones already removed
+    pub fn pipes_passed(&self) -> usize {
+        let behind = self
+            .pipes
+            .iter()
+            .filter(|pipe| pipe.x + pipe::WIDTH + pipe::LIP_OVERHANG < bird::X - bird::RADIUS)
+            .count();
+        self.current_score as usize + behind
+    }
+
+    /// Score of a bird dying now at height `y`, after surviving `ticks`
+    fn death_score(&self, ticks: usize) -> impl Fn(f64) -> f64 {
+        let fitness = Arc::clone(&self.fitness_fn);
+        let pipes_passed = self.pipes_passed();
+        let hole = self.pipes[self.first_pipe_index()].hole;
+        let height = self.height;
+        move |y| {
+            fitness.score(&Death {
+                ticks,
+                pipes_passed,
+                gap_distance: (y - hole).abs() / height,
+            })
+        }
+    }
+
+    fn handle_pipe_collision(&mut self, index: usize) {
+        let score = self.death_score(self.ticks);
+        let pipe_ref = &self.pipes[index];
+
+        let scores = &mut self.scores;
+        self.birds.retain(|bird_ref| {
+            let alive = !pipe_ref.swept_collides(
+                (bird::X, bird_ref.previous_y),
+                (bird::X, bird_ref.y),
+                bird::RADIUS,
+            );
+            if !alive {
+                scores[bird_ref.index] = score(bird_ref.y);
+            }
+            alive
+        });
+
+        if let Some(player_bird) = &self.player {
+            if pipe_ref.swept_collides(
+                (bird::X, player_bird.previous_y),
+                (bird::X, player_bird.y),
+                bird::RADIUS,
+            ) {
+                self.player.take();
+            }
+        }
+    }
+
+    pub fn handle_collisions(&mut self) {
+        let height = self.height;
+        let score = self.death_score(self.ticks);
+        self.ticks += 1;
+        let scores = &mut self.scores;
+
+        self.birds.retain(|bird_ref| {
+            let alive = bird_ref.y + bird::RADIUS <= height && bird_ref.y - bird::RADIUS >= 0.0;
+            if !alive {
+                scores[bird_ref.index] = score(bird_ref.y);
+            }
+            alive
+        });
+
+        if let Some(player_bird) = &self.player {
+            let alive =
+                player_bird.y + bird::RADIUS <= height && player_bird.y - bird::RADIUS >= 0.0;
+            if !alive {
+                self.player.take();
+            }
+        }
+
+        self.handle_pipe_collision(0);
+        self.handle_pipe_collision(1);
+
+        if !self.ended() && self.limit_reached() {
+            self.cap();
+        }
+    }
+
+    fn limit_reached(&self) -> bool {
+        (self.max_ticks > 0 && self.ticks >= self.max_ticks)
+            || (self.max_pipes > 0 && self.pipes_passed() >= self.max_pipes)
+    }
+
+    /// Scores the birds still alive and ends the game
+    fn cap(&mut self) {
+        let score = self.death_score(self.ticks);
+        for bird in self.birds.drain(..) {
+            self.scores[bird.index] = score(bird.y);
+        }
+        self.player = None;
+        self.capped = true;
+    }
+
+    /// Ends the game without scoring the birds still alive, dropping their networks
+    pub fn abort(&mut self) {
+        self.birds.clear();
+        self.player = None;
+    }
+
+    pub fn game_logic(&mut self) {
+        self.move_pipes();
+        self.apply_birds_velocity();
+    }
+
+    /// Advances the world by one tick
+    pub fn step(&mut self) {
+        self.make_decisions();
+        self.game_logic();
+        self.handle_collisions();
+    }
+
+    /// Steps until every bird is dead and returns the scores
+    pub fn run(&mut self) -> &[f64] {
+        while !self.ended() {
+            self.step();
+        }
+        &self.scores
+    }
+
+    fn random_color(&mut self) -> String {
+        // High value to not mix up with player
+        let c1 = self.rng.gen_range(100..255);
+        let c2 = self.rng.gen_range(100..255);
+        let c3 = self.rng.gen_range(100..255);
+
+        format!("rgb({}, {}, {})", c1, c2, c3)
+    }
+
+    pub fn ended(&self) -> bool {
+        self.birds.is_empty() && self.player.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Network of the three inputs with a single output, each input directly connected to it
+    fn topology(weights: [f64; 3], bias: f64) -> String {
+        let neuron_bias = |layer: usize, index: usize, bias: f64| {
+            format!(
+                r#"{{"neuron": [{}, {}], "bias": {{"bias_input": {:?}, "bias_update": 0.0, "bias_reset": 0.0}}}}"#,
+                layer, index, bias
+            )
+        };
+        let genes: Vec<String> = weights
+            .iter()
+            .enumerate()
+            .map(|(input, weight)| {
+                format!(
+                    r#"{{"connection_type": 0, "disabled": false, "input": [0, {}], "input_weight": {:?}, "memory_weight": 0.0, "output": [1, 0], "reset_input_weight": 0.0, "reset_memory_weight": 0.0, "update_input_weight": 0.0, "update_memory_weight": 0.0}}"#,
+                    input, weight
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"biases": [{}, {}, {}, {}], "genes": [{}]}}"#,
+            neuron_bias(0, 0, 0.0),
+            neuron_bias(0, 1, 0.0),
+            neuron_bias(0, 2, 0.0),
+            neuron_bias(1, 0, bias),
+            genes.join(", ")
+        )
+    }
+
+    /// Birds flying from a few ticks to several pipes
+    fn topologies() -> Vec<String> {
+        (0..8)
+            .map(|index| {
+                let index = index as f64;
+                topology([0.0, 5.0 - 0.1 * index, -1.0 + 0.05 * index], -1.0)
+            })
+            .collect()
+    }
+
+    fn networks(topologies: &[String]) -> Vec<NeuralNetwork<f64>> {
+        topologies
+            .iter()
+            .map(|topology| NeuralNetwork::from_string(topology))
+            .collect()
+    }
+
+    /// Plays until every bird is dead or `MAX_TICKS` passed, a good bird could fly forever
+    fn play(topologies: &[String], seed: u64) -> Simulation {
+        const MAX_TICKS: usize = 3000;
+        let mut simulation = Simulation::new(GameMode::Classic, 700.0, 800.0, 200, true, seed);
+        simulation.init(networks(topologies));
+        while !simulation.ended() && simulation.ticks() < MAX_TICKS {
+            simulation.step();
+        }
+        simulation
+    }
+
+    #[test]
+    fn same_seed_and_networks_give_the_same_game() {
+        let topologies = topologies();
+        let first = play(&topologies, 42);
+        let second = play(&topologies, 42);
+        assert_eq!(first.scores, second.scores);
+        assert_eq!(first.ticks(), second.ticks());
+        let heights = |simulation: &Simulation| -> Vec<f64> {
+            simulation.pipes().iter().map(|pipe| pipe.y).collect()
+        };
+        assert!(!first.pipes().is_empty());
+        assert_eq!(heights(&first), heights(&second));
+    }
+
+    #[test]
+    fn replays_give_the_recorded_scores() {
+        let mut recorded = Simulation::new(GameMode::Classic, 700.0, 800.0, 200, true, 42);
+        recorded.set_moving_pipes(50.0, 0.5);
+        recorded.set_limits(3000, 0);
+        recorded.record();
+        recorded.init(networks(&topologies()));
+        recorded.run();
+        let replay = Replay::from_json(&recorded.replay().unwrap().to_json()).unwrap();
+
+        let mut played = Simulation::from_replay(&replay).unwrap();
+        played.run();
+        assert_eq!(played.scores, recorded.scores);
+
+        let best = (0..recorded.scores.len())
+            .max_by(|&a, &b| recorded.scores[a].partial_cmp(&recorded.scores[b]).unwrap())
+            .unwrap();
+        let mut alone = Simulation::from_replay(&replay.bird(best).unwrap()).unwrap();
+        alone.run();
+        assert_eq!(alone.scores, vec![recorded.scores[best]]);
+    }
+
+    /// Scores the birds by the square of the pipes they passed
+    struct SquaredPipes;
+
+    impl FitnessFn for SquaredPipes {
+        fn score(&self, death: &Death) -> f64 {
+            (death.pipes_passed * death.pipes_passed) as f64
+        }
+    }
+
+    #[test]
+    fn custom_fitness_scores_the_birds() {
+        let mut counted = Simulation::new(GameMode::Classic, 700.0, 800.0, 200, true, 42);
+        counted.set_limits(3000, 0);
+        counted.set_fitness(Fitness::PipesPassed);
+        counted.init(networks(&topologies()));
+        counted.run();
+
+        let mut custom = Simulation::new(GameMode::Classic, 700.0, 800.0, 200, true, 42);
+        custom.set_limits(3000, 0);
+        custom.set_custom_fitness(SquaredPipes);
+        custom.record();
+        custom.init(networks(&topologies()));
+        custom.run();
+
+        let squared: Vec<f64> = counted.scores.iter().map(|pipes| pipes * pipes).collect();
+        assert!(squared.iter().any(|&score| score > 1.0));
+        assert_eq!(custom.scores, squared);
+        assert!(custom.replay().is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::game::draw::{self, DrawCommand, DrawList, Hud};
+use crate::game::simulation::Simulation;
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+/// Drawing state tracked while converting the commands, like a canvas context does
+struct State {
+    fill_style: String,
+    stroke_style: String,
+    line_width: f64,
+    font: String,
+    /// Shapes added since the last `BeginPath`, without their style
+    path: Vec<String>,
+    /// Last point of the path, where `LineTo` starts
+    point: (f64, f64),
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// SVG has no negative sizes, the canvas draws those towards the origin
+fn normalize(start: f64, size: f64) -> (f64, f64) {
+    if size < 0.0 {
+        (start + size, -size)
+    } else {
+        (start, size)
+    }
+}
+
+fn arc(x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64) -> String {
+    if (end_angle - start_angle).abs() >= PI * 2.0 {
+        return format!(r#"<circle cx="{}" cy="{}" r="{}""#, x, y, radius);
+    }
+    let (start_x, start_y) = (
+        x + radius * start_angle.cos(),
+        y + radius * start_angle.sin(),
+    );
+    let (end_x, end_y) = (x + radius * end_angle.cos(), y + radius * end_angle.sin());
+    let large_arc = ((end_angle - start_angle).rem_euclid(PI * 2.0) > PI) as u8;
+    format!(
+        r#"<path d="M {} {} A {} {} 0 {} 1 {} {}""#,
+        start_x, start_y, radius, radius, large_arc, end_x, end_y
+    )
+}
+
+/// SVG backend, turns the commands of a frame into a standalone SVG document.
+///
+/// Partial ellipses are drawn whole, the game only draws full ones
+pub fn to_svg(draw: &DrawList, width: f64, height: f64) -> String {
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width, height, width, height
+    );
+    svg.push('\n');
+    let mut state = State {
+        fill_style: String::from("black"),
+        stroke_style: String::from("black"),
+        line_width: 1.0,
+        font: String::from("10px sans-serif"),
+        path: Vec::new(),
+        point: (0.0, 0.0),
+    };
+    for command in draw.commands() {
+        match command {
+            DrawCommand::BeginPath => state.path.clear(),
+            DrawCommand::FillStyle(style) => state.fill_style = style.clone(),
+            DrawCommand::StrokeStyle(style) => state.stroke_style = style.clone(),
+            DrawCommand::LineWidth(width) => state.line_width = *width,
+            DrawCommand::Font(font) => state.font = font.clone(),
+            DrawCommand::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (x, width) = normalize(*x, *width);
+                let (y, height) = normalize(*y, *height);
+                state.path.push(format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}""#,
+                    x, y, width, height
+                ));
+            }
+            DrawCommand::Arc {
+                x,
+                y,
+                radius,
+                start_angle,
+                end_angle,
+            } => state
+                .path
+                .push(arc(*x, *y, *radius, *start_angle, *end_angle)),
+            DrawCommand::Ellipse {
+                x,
+                y,
+                radius_x,
+                radius_y,
+                rotation,
+                ..
+            } => state.path.push(format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" transform="rotate({} {} {})""#,
+                x,
+                y,
+                radius_x,
+                radius_y,
+                rotation.to_degrees(),
+                x,
+                y
+            )),
+            DrawCommand::MoveTo { x, y } => state.point = (*x, *y),
+            DrawCommand::LineTo { x, y } => {
+                let (from_x, from_y) = state.point;
+                state.path.push(format!(
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}""#,
+                    from_x, from_y, x, y
+                ));
+                state.point = (*x, *y);
+            }
+            DrawCommand::Fill => {
+                for shape in &state.path {
+                    writeln!(svg, r#"{} fill="{}"/>"#, shape, escape(&state.fill_style)).unwrap();
+                }
+            }
+            DrawCommand::Stroke => {
+                for shape in &state.path {
+                    writeln!(
+                        svg,
+                        r#"{} fill="none" stroke="{}" stroke-width="{}"/>"#,
+                        shape,
+                        escape(&state.stroke_style),
+                        state.line_width
+                    )
+                    .unwrap();
+                }
+            }
+            DrawCommand::FillText { text, x, y } => {
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" style="font: {}" fill="{}">{}</text>"#,
+                    x,
+                    y,
+                    escape(&state.font),
+                    escape(&state.fill_style),
+                    escape(text)
+                )
+                .unwrap();
+            }
+            // Snapshots have a white background where the canvas is transparent
+            DrawCommand::ClearRect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="white"/>"#,
+                    x, y, width, height
+                )
+                .unwrap();
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// SVG document of the current frame of a simulation, with at most `render_count` birds
+pub fn snapshot(simulation: &Simulation, render_count: usize, hud: &Hud) -> String {
+    let frame = draw::draw_frame(simulation, render_count, hud);
+    to_svg(&frame, simulation.width(), simulation.height())
+}
@@ -1,15 +1,45 @@
 extern crate serde;
 
-mod game;
-mod training_simulation;
+pub mod champions;
+pub mod chart;
+mod controls;
+pub mod curriculum;
+pub mod game;
+pub mod statistics;
+pub mod training_simulation;
 mod utils;
 
+use crate::champions::Champions;
+use crate::curriculum::Schedule;
+use crate::game::fitness::Fitness;
+use crate::game::game::Game;
+use crate::game::mode::GameMode;
+use crate::game::physics::Physics;
+use crate::game::replay::Replay;
+use crate::game::sensors::Sensors;
+use crate::game::simulation::{self, Simulation};
+use crate::statistics::{Aggregation, Statistics};
 use crate::training_simulation::TrainingSimulation;
-use crate::utils::set_panic_hook;
+use crate::utils::{download, scaled_context, set_panic_hook};
+use futures::channel::oneshot;
+use futures::future::{self, Either};
+use futures::pin_mut;
 use neat_gru::train::Train;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 
+thread_local! {
+    /// Recording of the last finished generation in the browser
+    static LAST_REPLAY: RefCell<Option<Replay>> = const { RefCell::new(None) };
+
+    /// Statistics of the training running in the browser
+    static STATISTICS: RefCell<Statistics> = RefCell::new(Statistics::new());
+}
+
 #[wasm_bindgen]
 extern "C" {
     fn alert(s: &str);
@@ -18,28 +48,90 @@ extern "C" {
     pub fn log(s: &str);
 }
 
-async fn run_training(params: GameParams) {
-    let outputs_count = if params.game_type == 0 { 1 } else { 2 };
+/// Applies the NEAT settings shared by the browser and the native trainer
+pub fn configure_training(runner: &mut Train<TrainingSimulation, f64>, params: &GameParams) {
     let birds_count = params.birds_count;
 
-    let mut sim = TrainingSimulation::new(700.0, 800.0, params);
-    let mut runner: Train<TrainingSimulation, f64> = Train::new(&mut sim);
-
     runner
-        .inputs(3)
-        .outputs(outputs_count)
-        .iterations(5000)
-        .delta_threshold(2.)
-        .formula(0.8, 0.8, 0.3)
-        .max_layers(10)
-        .max_per_layers(10)
+        .inputs(params.sensors().len())
+        .outputs(params.mode().outputs())
+        .iterations(params.iterations as usize)
+        .delta_threshold(params.delta_threshold)
+        .formula(params.c1, params.c2, params.c3)
+        .max_layers(params.max_layers as usize)
+        .max_per_layers(params.max_per_layers as usize)
         .max_individuals(birds_count as usize)
         .access_train_object(Box::new(|train| {
             let species_count = train.species_count();
             train.simulation.species_count = species_count;
-        }))
-        .start_async()
-        .await;
+        }));
+}
+
+/// Draws the fitness chart of the training on the `#chart` canvas
+fn render_chart(statistics: &Statistics) {
+    let context = scaled_context("chart", chart::WIDTH, chart::HEIGHT);
+    chart::draw_chart(statistics.generations(), chart::WIDTH, chart::HEIGHT)
+        .draw_on_canvas(&context);
+}
+
+/// Handle on the training started by `start`
+#[wasm_bindgen]
+pub struct TrainingHandle {
+    cancel: Option<oneshot::Sender<()>>,
+    /// Ends the game of the training, and only that one
+    stop: Arc<AtomicBool>,
+}
+
+#[wasm_bindgen]
+impl TrainingHandle {
+    /// Ends the training and the game on the canvas, `start` can be called again afterwards
+    pub fn stop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+async fn run_training(params: GameParams, stop: Arc<AtomicBool>, cancel: oneshot::Receiver<()>) {
+    let mut sim = TrainingSimulation::new(params.clone());
+    sim.set_stop_flag(stop);
+    sim.on_replay(Box::new(|replay| {
+        LAST_REPLAY.with(|last| *last.borrow_mut() = Some(replay));
+    }));
+    STATISTICS.with(|statistics| *statistics.borrow_mut() = Statistics::new());
+    sim.on_generation(Box::new(|stats| {
+        STATISTICS.with(|statistics| {
+            let mut statistics = statistics.borrow_mut();
+            statistics.record(stats.clone());
+            render_chart(&statistics);
+        });
+    }));
+    let finished = {
+        let training = async {
+            let mut runner: Train<TrainingSimulation, f64> = Train::new(&mut sim);
+
+            configure_training(&mut runner, &params);
+            runner.start_async().await.unwrap();
+        };
+        // A handle freed by JS without calling `stop` doesn't end the training
+        let stopped = async {
+            if cancel.await.is_err() {
+                future::pending::<()>().await;
+            }
+        };
+        pin_mut!(training, stopped);
+        // Dropping the training future frees the networks
+        matches!(future::select(training, stopped).await, Either::Left(_))
+    };
+    if finished {
+        if let Some(champions) = sim.champions() {
+            download("champions.json", &champions.to_json(), "application/json");
+        }
+    }
+    let document = web_sys::window().unwrap().document().unwrap();
+    let start_button = document.get_element_by_id("start").unwrap();
+    start_button.remove_attribute("style").unwrap();
 }
 
 #[wasm_bindgen]
@@ -48,19 +140,229 @@ pub struct GameParams {
     pub birds_count: i32,
     pub render_count: i32,
     pub hole_size: i32,
+    /// Size of the world, the canvas scales it to its own size
+    pub width: f64,
+    pub height: f64,
+    /// Makes the pipes accelerate over time, the browser reads the `#speed` checkbox instead
+    pub speed: bool,
+    /// Seed of the pipes and colors, every generation derives its own course from it
+    pub seed: u32,
+    /// Number of generations to run
+    pub iterations: u32,
+    /// Compatibility distance above which two topologies belong to different species
+    pub delta_threshold: f64,
+    /// Weight of the excess genes in the compatibility distance
+    pub c1: f64,
+    /// Weight of the disjoint genes in the compatibility distance
+    pub c2: f64,
+    /// Weight of the average weight difference in the compatibility distance
+    pub c3: f64,
+    pub max_layers: u32,
+    pub max_per_layers: u32,
+    /// Bit mask of the `Sensor`s fed to the networks, in declaration order
+    pub sensors: u32,
+    /// Id of the `Fitness` the birds are scored with
+    pub fitness: i32,
+    /// How far the gaps move up and down, 0 keeps the pipes still
+    pub pipe_amplitude: f64,
+    /// Oscillations of the gaps per 100 ticks
+    pub pipe_frequency: f64,
+    /// Id of the curriculum `Schedule` shrinking the hole size down to `hole_size`
+    pub curriculum: i32,
+    /// Hole size of the first generation when a curriculum is used
+    pub curriculum_start: i32,
+    /// Generations to reach `hole_size` with the linear schedule, between two shrinks with
+    /// the step one
+    pub curriculum_generations: u32,
+    /// Pixels removed at every shrink of the step and performance schedules
+    pub curriculum_step: i32,
+    /// Pipes a generation must pass for the performance schedule to shrink the hole
+    pub curriculum_threshold: f64,
+    /// Ticks after which a generation ends with its birds still alive, 0 for no limit
+    pub max_ticks: u32,
+    /// Pipes after which a generation ends with its birds still alive, 0 for no limit
+    pub max_pipes: u32,
+    /// Courses every generation is played on, each with its own seed
+    pub episodes: u32,
+    /// Id of the `Aggregation` turning the scores of the episodes into the fitness of a bird
+    pub aggregation: i32,
+    /// `Physics` of the world, the preset of `game_type` unless changed
+    pub gravity: f64,
+    pub jump_impulse: f64,
+    pub pipe_speed: f64,
+    pub pipe_spacing: f64,
+    pub speed_increase: f64,
+    pub speed_increase_rate: f64,
 }
 
 #[wasm_bindgen]
 impl GameParams {
     #[wasm_bindgen(constructor)]
     pub fn new(game_type: i32, birds_count: i32, render_count: i32, hole_size: i32) -> GameParams {
+        // `validate` rejects invalid game types later on
+        let physics = GameMode::try_from(game_type)
+            .map(GameMode::physics)
+            .unwrap_or_else(|_| Physics::classic());
         GameParams {
             game_type,
             birds_count,
             render_count,
             hole_size,
+            width: 700.0,
+            height: 800.0,
+            speed: false,
+            seed: rand::random(),
+            iterations: 5000,
+            delta_threshold: 2.,
+            c1: 0.8,
+            c2: 0.8,
+            c3: 0.3,
+            max_layers: 10,
+            max_per_layers: 10,
+            sensors: Sensors::default().mask(),
+            fitness: Fitness::default().id(),
+            pipe_amplitude: 0.0,
+            pipe_frequency: 0.5,
+            curriculum: Schedule::default().id(),
+            curriculum_start: 300,
+            curriculum_generations: 50,
+            curriculum_step: 10,
+            curriculum_threshold: 5.0,
+            max_ticks: 0,
+            max_pipes: 0,
+            episodes: 1,
+            aggregation: Aggregation::default().id(),
+            gravity: physics.gravity,
+            jump_impulse: physics.jump_impulse,
+            pipe_speed: physics.pipe_speed,
+            pipe_spacing: physics.pipe_spacing,
+            speed_increase: physics.speed_increase,
+            speed_increase_rate: physics.speed_increase_rate,
+        }
+    }
+
+    /// Sets the physics to the preset of `game_type`
+    pub fn use_physics_preset(&mut self) -> Result<(), JsValue> {
+        self.set_physics(GameMode::try_from(self.game_type)?.physics());
+        Ok(())
+    }
+}
+
+impl GameParams {
+    /// Physics of the game, `validate` makes sure the game type is valid
+    pub fn mode(&self) -> GameMode {
+        GameMode::try_from(self.game_type).unwrap()
+    }
+
+    /// Inputs of the networks, `validate` makes sure the mask is valid
+    pub fn sensors(&self) -> Sensors {
+        Sensors::from_mask(self.sensors).unwrap()
+    }
+
+    /// Scoring of the birds, `validate` makes sure the id is valid
+    pub fn fitness(&self) -> Fitness {
+        Fitness::try_from(self.fitness).unwrap()
+    }
+
+    /// How the episodes are combined, `validate` makes sure the id is valid
+    pub fn aggregation(&self) -> Aggregation {
+        Aggregation::try_from(self.aggregation).unwrap()
+    }
+
+    pub fn physics(&self) -> Physics {
+        Physics {
+            gravity: self.gravity,
+            jump_impulse: self.jump_impulse,
+            pipe_speed: self.pipe_speed,
+            pipe_spacing: self.pipe_spacing,
+            speed_increase: self.speed_increase,
+            speed_increase_rate: self.speed_increase_rate,
         }
     }
+
+    /// Physics of a game of the given mode, e.g. for champions trained in another mode: the
+    /// preset of that mode unless the parameters change the preset of their own mode
+    pub fn physics_for(&self, mode: GameMode) -> Physics {
+        let physics = self.physics();
+        let preset = GameMode::try_from(self.game_type).map(GameMode::physics);
+        if preset == Ok(physics) {
+            mode.physics()
+        } else {
+            physics
+        }
+    }
+
+    pub fn set_physics(&mut self, physics: Physics) {
+        self.gravity = physics.gravity;
+        self.jump_impulse = physics.jump_impulse;
+        self.pipe_speed = physics.pipe_speed;
+        self.pipe_spacing = physics.pipe_spacing;
+        self.speed_increase = physics.speed_increase;
+        self.speed_increase_rate = physics.speed_increase_rate;
+    }
+
+    /// Checks the parameters before a training starts
+    pub fn validate(&self) -> Result<(), String> {
+        GameMode::try_from(self.game_type)?;
+        Fitness::try_from(self.fitness)?;
+        Aggregation::try_from(self.aggregation)?;
+        self.physics().validate()?;
+        if self.episodes < 1 {
+            return Err(String::from("There must be at least one episode"));
+        }
+        if self.birds_count < 1 {
+            return Err(String::from("There must be at least one bird"));
+        }
+        simulation::validate_world(self.width, self.height, self.hole_size)?;
+        if self.iterations < 1 {
+            return Err(String::from("There must be at least one iteration"));
+        }
+        if !self.delta_threshold.is_finite() || self.delta_threshold <= 0.0 {
+            return Err(String::from("The delta threshold must be positive"));
+        }
+        for (name, c) in [("c1", self.c1), ("c2", self.c2), ("c3", self.c3)] {
+            if !c.is_finite() || c < 0.0 {
+                return Err(format!("{} can't be negative", name));
+            }
+        }
+        if !self.pipe_amplitude.is_finite() || self.pipe_amplitude < 0.0 {
+            return Err(String::from("The pipe amplitude can't be negative"));
+        }
+        if !self.pipe_frequency.is_finite() || self.pipe_frequency < 0.0 {
+            return Err(String::from("The pipe frequency can't be negative"));
+        }
+        if Schedule::try_from(self.curriculum)? != Schedule::Fixed {
+            if self.curriculum_start < self.hole_size {
+                return Err(String::from(
+                    "The curriculum must start with a hole at least as big as the hole size",
+                ));
+            }
+            if self.height <= self.curriculum_start as f64 {
+                return Err(String::from(
+                    "The world must be higher than the first hole of the curriculum",
+                ));
+            }
+            if self.curriculum_generations < 1 {
+                return Err(String::from(
+                    "The curriculum must last at least one generation",
+                ));
+            }
+            if self.curriculum_step < 1 {
+                return Err(String::from("The curriculum step must be positive"));
+            }
+            if !self.curriculum_threshold.is_finite() || self.curriculum_threshold < 0.0 {
+                return Err(String::from("The curriculum threshold can't be negative"));
+            }
+        }
+        // Input and output layers
+        if self.max_layers < 2 {
+            return Err(String::from("There must be at least two layers"));
+        }
+        if self.max_per_layers < 1 {
+            return Err(String::from("There must be at least one neuron per layer"));
+        }
+        Ok(())
+    }
 }
 
 impl Clone for GameParams {
@@ -70,17 +372,147 @@ impl Clone for GameParams {
             birds_count: self.birds_count,
             render_count: self.render_count,
             hole_size: self.hole_size,
+            width: self.width,
+            height: self.height,
+            speed: self.speed,
+            seed: self.seed,
+            iterations: self.iterations,
+            delta_threshold: self.delta_threshold,
+            c1: self.c1,
+            c2: self.c2,
+            c3: self.c3,
+            max_layers: self.max_layers,
+            max_per_layers: self.max_per_layers,
+            sensors: self.sensors,
+            fitness: self.fitness,
+            pipe_amplitude: self.pipe_amplitude,
+            pipe_frequency: self.pipe_frequency,
+            curriculum: self.curriculum,
+            curriculum_start: self.curriculum_start,
+            curriculum_generations: self.curriculum_generations,
+            curriculum_step: self.curriculum_step,
+            curriculum_threshold: self.curriculum_threshold,
+            max_ticks: self.max_ticks,
+            max_pipes: self.max_pipes,
+            episodes: self.episodes,
+            aggregation: self.aggregation,
+            gravity: self.gravity,
+            jump_impulse: self.jump_impulse,
+            pipe_speed: self.pipe_speed,
+            pipe_spacing: self.pipe_spacing,
+            speed_increase: self.speed_increase,
+            speed_increase_rate: self.speed_increase_rate,
         }
     }
 }
 
 #[wasm_bindgen]
-pub fn start(params: GameParams) {
+pub fn start(params: GameParams) -> Result<TrainingHandle, JsValue> {
     set_panic_hook();
+    params.validate().map_err(|err| JsValue::from_str(&err))?;
     let document = web_sys::window().unwrap().document().unwrap();
     let start_button = document.get_element_by_id("start").unwrap();
     start_button
         .set_attribute("style", "display: none;")
         .unwrap();
-    spawn_local(run_training(params));
+    let (cancel, cancelled) = oneshot::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    spawn_local(run_training(params, stop.clone(), cancelled));
+    Ok(TrainingHandle {
+        cancel: Some(cancel),
+        stop,
+    })
+}
+
+/// Downloads the replay of the last finished generation, or of a single bird of it
+#[wasm_bindgen]
+pub fn download_replay(bird: Option<u32>) -> Result<(), JsValue> {
+    let replay = LAST_REPLAY.with(|last| last.borrow().clone());
+    let replay = replay.ok_or_else(|| JsValue::from_str("No generation has finished yet"))?;
+    let replay = match bird {
+        Some(index) => replay
+            .bird(index as usize)
+            .ok_or_else(|| JsValue::from_str(&format!("No bird with index {}", index)))?,
+        None => replay,
+    };
+    download("replay.json", &replay.to_json(), "application/json");
+    Ok(())
+}
+
+/// Downloads the statistics of the current training, `format` is either "csv" or "jsonl"
+#[wasm_bindgen]
+pub fn download_statistics(format: &str) -> Result<(), JsValue> {
+    let (filename, contents, mime_type) = STATISTICS.with(|statistics| {
+        let statistics = statistics.borrow();
+        match format {
+            "csv" => Ok(("statistics.csv", statistics.to_csv(), "text/csv")),
+            "jsonl" => Ok((
+                "statistics.jsonl",
+                statistics.to_json_lines(),
+                "application/jsonl",
+            )),
+            _ => Err(JsValue::from_str(&format!("Unknown format {}", format))),
+        }
+    })?;
+    download(filename, &contents, mime_type);
+    Ok(())
+}
+
+/// Downloads the frame currently on the canvas as an SVG document
+#[wasm_bindgen]
+pub fn download_snapshot() -> Result<(), JsValue> {
+    let game = Game::current().ok_or_else(|| JsValue::from_str("No game has started yet"))?;
+    let svg = game.lock().unwrap().snapshot_svg();
+    download("snapshot.svg", &svg, "image/svg+xml");
+    Ok(())
+}
+
+/// Plays a replay previously downloaded with `download_replay` on the canvas
+#[wasm_bindgen]
+pub fn play_replay(serialized: &str) -> Result<(), JsValue> {
+    set_panic_hook();
+    let replay = Replay::from_json(serialized).map_err(|err| JsValue::from_str(&err))?;
+    spawn_local(async move {
+        if let Err(err) = Game::play_replay(&replay).await {
+            log(&err);
+        }
+    });
+    Ok(())
+}
+
+/// Plays the champions saved at the end of a training on the canvas
+#[wasm_bindgen]
+pub fn play_champions(serialized: &str, params: GameParams) -> Result<(), JsValue> {
+    set_panic_hook();
+    params.validate().map_err(|err| JsValue::from_str(&err))?;
+    let champions = Champions::from_json(serialized).map_err(|err| JsValue::from_str(&err))?;
+    let mode = champions.mode()?;
+    let sensors = champions.sensors()?;
+    let fitness = Fitness::try_from(params.fitness)?;
+    let physics = params.physics_for(mode);
+    physics.validate()?;
+    let networks = champions.networks();
+    let layouts = champions.layouts()?;
+    let render_count = networks.len() as i32;
+    let seed = params.seed as u64;
+    spawn_local(async move {
+        let mut simulation = Simulation::new(
+            mode,
+            params.width,
+            params.height,
+            params.hole_size,
+            params.speed,
+            seed,
+        );
+        simulation.set_sensors(sensors);
+        simulation.set_fitness(fitness);
+        simulation.set_physics(physics);
+        simulation.set_moving_pipes(params.pipe_amplitude, params.pipe_frequency);
+        simulation.set_limits(params.max_ticks as usize, params.max_pipes as usize);
+        simulation.init(networks);
+        simulation.set_layouts(layouts);
+        let stop = Arc::new(AtomicBool::new(false));
+        Game::run_game(simulation, render_count, 0, 0, stop).await;
+    });
+    Ok(())
 }
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Middle of sorted values, the average of the two middle ones for an even count
+fn median(sorted: &[f64]) -> f64 {
+    let len = sorted.len();
+    if len % 2 == 1 {
+        sorted[len / 2]
+    } else {
+        (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0
+    }
+}
+
+/// Combines the scores a bird got on the episodes of a generation into its fitness
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    /// Rewards the birds doing well on every course
+    Min,
+    Median,
+}
+
+pub const ALL_AGGREGATIONS: [Aggregation; 3] =
+    [Aggregation::Mean, Aggregation::Min, Aggregation::Median];
+
+impl Aggregation {
+    /// Identifier used by `GameParams`
+    pub fn id(self) -> i32 {
+        match self {
+            Aggregation::Mean => 0,
+            Aggregation::Min => 1,
+            Aggregation::Median => 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aggregation::Mean => "mean",
+            Aggregation::Min => "min",
+            Aggregation::Median => "median",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Aggregation, String> {
+        ALL_AGGREGATIONS
+            .iter()
+            .cloned()
+            .find(|aggregation| aggregation.name() == name)
+            .ok_or(format!("Unknown aggregation: {}", name))
+    }
+
+    /// Combines the scores of every episode, bird by bird
+    pub fn combine(self, episodes: &[Vec<f64>]) -> Vec<f64> {
+        let birds = episodes.first().map_or(0, |scores| scores.len());
+        (0..birds)
+            .map(|bird| {
+                let mut scores: Vec<f64> = episodes.iter().map(|scores| scores[bird]).collect();
+                match self {
+                    Aggregation::Mean => scores.iter().sum::<f64>() / scores.len() as f64,
+                    Aggregation::Min => scores.iter().cloned().fold(f64::INFINITY, f64::min),
+                    Aggregation::Median => {
+                        scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                        median(&scores)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<i32> for Aggregation {
+    type Error = String;
+
+    fn try_from(id: i32) -> Result<Aggregation, String> {
+        ALL_AGGREGATIONS
+            .iter()
+            .cloned()
+            .find(|aggregation| aggregation.id() == id)
+            .ok_or(format!("Invalid aggregation: {}", id))
+    }
+}
+
+/// Summary of a finished generation
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GenerationStats {
+    pub generation: usize,
+    pub best: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub worst: f64,
+    pub species_count: usize,
+    pub pipes_passed: f64,
+    pub ticks: usize,
+    pub hole_size: i32,
+    /// Whether the generation hit the tick or pipe limit with birds still alive
+    pub capped: bool,
+}
+
+impl GenerationStats {
+    pub fn new(
+        generation: usize,
+        scores: &[f64],
+        species_count: usize,
+        pipes_passed: f64,
+        ticks: usize,
+        hole_size: i32,
+        capped: bool,
+    ) -> GenerationStats {
+        let mut sorted = scores.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let len = sorted.len();
+        let (best, worst, mean, median) = if len == 0 {
+            (0.0, 0.0, 0.0, 0.0)
+        } else {
+            let mean = sorted.iter().sum::<f64>() / len as f64;
+            (sorted[len - 1], sorted[0], mean, median(&sorted))
+        };
+        GenerationStats {
+            generation,
+            best,
+            mean,
+            median,
+            worst,
+            species_count,
+            pipes_passed,
+            ticks,
+            hole_size,
+            capped,
+        }
+    }
+}
+
+/// Collects the statistics of every generation of a training
+#[derive(Default, Clone, Debug)]
+pub struct Statistics {
+    generations: Vec<GenerationStats>,
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    pub fn record(&mut self, stats: GenerationStats) {
+        self.generations.push(stats);
+    }
+
+    pub fn generations(&self) -> &[GenerationStats] {
+        &self.generations
+    }
+
+    pub fn csv_header() -> &'static str {
+        "generation,best,mean,median,worst,species_count,pipes_passed,ticks,hole_size,capped"
+    }
+
+    pub fn csv_line(stats: &GenerationStats) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            stats.generation,
+            stats.best,
+            stats.mean,
+            stats.median,
+            stats.worst,
+            stats.species_count,
+            stats.pipes_passed,
+            stats.ticks,
+            stats.hole_size,
+            stats.capped
+        )
+    }
+
+    pub fn json_line(stats: &GenerationStats) -> String {
+        serde_json::to_string(stats).unwrap()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(Statistics::csv_header());
+        csv.push('\n');
+        for stats in &self.generations {
+            csv.push_str(&Statistics::csv_line(stats));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// One JSON object per generation and per line
+    pub fn to_json_lines(&self) -> String {
+        let mut lines = String::new();
+        for stats in &self.generations {
+            lines.push_str(&Statistics::json_line(stats));
+            lines.push('\n');
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scores of three birds on three courses
+    fn episodes() -> Vec<Vec<f64>> {
+        vec![
+            vec![1.0, 10.0, 4.0],
+            vec![4.0, 2.0, 4.0],
+            vec![7.0, 0.0, 4.0],
+        ]
+    }
+
+    #[test]
+    fn aggregations_combine_the_courses_per_bird() {
+        assert_eq!(Aggregation::Mean.combine(&episodes()), vec![4.0, 4.0, 4.0]);
+        assert_eq!(Aggregation::Min.combine(&episodes()), vec![1.0, 0.0, 4.0]);
+        assert_eq!(
+            Aggregation::Median.combine(&episodes()),
+            vec![4.0, 2.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn aggregations_of_a_single_course_keep_the_scores() {
+        let scores = vec![vec![3.0, 1.5, 8.0]];
+        for aggregation in ALL_AGGREGATIONS.iter() {
+            assert_eq!(aggregation.combine(&scores), scores[0]);
+        }
+    }
+
+    #[test]
+    fn aggregations_of_no_course_give_no_score() {
+        assert!(Aggregation::Mean.combine(&[]).is_empty());
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&[1.0, 2.0, 7.0]), 2.0);
+        assert_eq!(median(&[1.0, 2.0, 4.0, 7.0]), 3.0);
+        assert_eq!(median(&[5.0]), 5.0);
+    }
+
+    #[test]
+    fn generation_stats_sort_the_scores() {
+        let stats = GenerationStats::new(3, &[4.0, 1.0, 10.0, 5.0], 2, 1.5, 300, 200, false);
+        assert_eq!(stats.best, 10.0);
+        assert_eq!(stats.worst, 1.0);
+        assert_eq!(stats.mean, 5.0);
+        assert_eq!(stats.median, 4.5);
+    }
+
+    #[test]
+    fn generation_stats_of_no_bird() {
+        let stats = GenerationStats::new(0, &[], 1, 0.0, 0, 200, false);
+        assert_eq!(
+            (stats.best, stats.mean, stats.median, stats.worst),
+            (0.0, 0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn csv_has_a_header_and_a_row_per_generation() {
+        let mut statistics = Statistics::new();
+        statistics.record(GenerationStats::new(
+            0,
+            &[1.0, 3.0],
+            1,
+            0.5,
+            120,
+            250,
+            false,
+        ));
+        statistics.record(GenerationStats::new(1, &[2.0, 6.0], 2, 1.0, 400, 240, true));
+        let csv = statistics.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "generation,best,mean,median,worst,species_count,pipes_passed,ticks,hole_size,capped"
+        );
+        assert_eq!(lines[1], "0,3,2,2,1,1,0.5,120,250,false");
+        assert_eq!(lines[2], "1,6,4,4,2,2,1,400,240,true");
+        let columns = lines[0].split(',').count();
+        assert!(lines.iter().all(|line| line.split(',').count() == columns));
+    }
+
+    #[test]
+    fn json_lines_read_back() {
+        let mut statistics = Statistics::new();
+        statistics.record(GenerationStats::new(
+            0,
+            &[1.0, 3.0],
+            1,
+            0.5,
+            120,
+            250,
+            false,
+        ));
+        let line = statistics.to_json_lines();
+        let stats: GenerationStats = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(stats.generation, 0);
+        assert_eq!(stats.best, 3.0);
+        assert_eq!(stats.ticks, 120);
+    }
+}
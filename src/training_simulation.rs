@@ -1,90 +1,217 @@
+use crate::champions::{Champions, CHAMPIONS_COUNT};
+use crate::curriculum::Curriculum;
 use crate::game::game::Game;
+use crate::game::replay::Replay;
+use crate::game::simulation::Simulation;
+use crate::statistics::{GenerationStats, Statistics};
 use crate::GameParams;
 use async_trait::async_trait;
 use neat_gru::game::GameAsync;
 use neat_gru::neural_network::NeuralNetwork;
 use neat_gru::topology::Topology;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Called with the recording of every generation
+pub type ReplayCallback = Box<dyn FnMut(Replay)>;
+
+/// Called with the statistics of every generation
+pub type GenerationCallback = Box<dyn FnMut(&GenerationStats)>;
+
+/// What is kept of a course once it is over
+struct Episode {
+    scores: Vec<f64>,
+    pipes_passed: f64,
+    ticks: usize,
+    hole_size: i32,
+    capped: bool,
+}
 
 pub struct TrainingSimulation {
-    width: f64,
-    height: f64,
     params: GameParams,
     networks: Option<Vec<NeuralNetwork<f64>>>,
     generation: usize,
     pub species_count: usize,
+    on_replay: Option<ReplayCallback>,
+    champions: Option<Champions>,
+    statistics: Statistics,
+    on_generation: Option<GenerationCallback>,
+    curriculum: Curriculum,
+    /// Ends the game on the canvas when set
+    stop: Arc<AtomicBool>,
 }
 
 unsafe impl Send for TrainingSimulation {}
 unsafe impl Sync for TrainingSimulation {}
 
 impl TrainingSimulation {
-    pub fn new(width: f64, height: f64, params: GameParams) -> TrainingSimulation {
+    pub fn new(params: GameParams) -> TrainingSimulation {
         TrainingSimulation {
-            width,
-            height,
+            curriculum: Curriculum::new(&params),
             params,
             generation: 0,
             networks: None,
             species_count: 1,
+            on_replay: None,
+            champions: None,
+            statistics: Statistics::new(),
+            on_generation: None,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Flag ending the generation being played on the canvas once set, for cancelling the
+    /// training
+    pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    /// Records every generation and hands the replay to `callback`
+    pub fn on_replay(&mut self, callback: ReplayCallback) {
+        self.on_replay = Some(callback);
+    }
+
+    /// Hands the statistics of every generation to `callback` as soon as it is over
+    pub fn on_generation(&mut self, callback: GenerationCallback) {
+        self.on_generation = Some(callback);
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Best networks of the run, available once the training is over
+    pub fn champions(&self) -> Option<&Champions> {
+        self.champions.as_ref()
+    }
+
+    /// Seed of the course of a given generation, derived from the seed of the run
+    fn generation_seed(&self, generation: usize) -> u64 {
+        ((self.params.seed as u64) << 32) | generation as u64
+    }
+
+    /// Seed of one of the courses of a generation, the first one is the generation's own
+    fn episode_seed(&self, generation: usize, episode: usize) -> u64 {
+        self.generation_seed(generation) ^ (episode as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    /// Creates the world of an episode of the current generation, only the first one is
+    /// recorded
+    fn new_simulation(&self, episode: usize) -> Simulation {
+        let mut simulation = Simulation::new(
+            self.params.mode(),
+            self.params.width,
+            self.params.height,
+            self.curriculum.hole_size(),
+            self.params.speed,
+            self.episode_seed(self.generation, episode),
+        );
+        simulation.set_sensors(self.params.sensors());
+        simulation.set_fitness(self.params.fitness());
+        simulation.set_physics(self.params.physics());
+        simulation.set_moving_pipes(self.params.pipe_amplitude, self.params.pipe_frequency);
+        simulation.set_limits(
+            self.params.max_ticks as usize,
+            self.params.max_pipes as usize,
+        );
+        if episode == 0 && self.on_replay.is_some() {
+            simulation.record();
+        }
+        // Every episode starts from networks that never ran
+        simulation.init(self.networks.as_ref().unwrap().clone());
+        simulation
+    }
+
+    /// Collects the results of a finished episode
+    fn end_episode(&mut self, simulation: &Simulation) -> Episode {
+        if let (Some(callback), Some(replay)) = (&mut self.on_replay, simulation.replay()) {
+            callback(replay);
         }
+        Episode {
+            scores: simulation.scores.clone(),
+            pipes_passed: simulation.pipes_passed() as f64,
+            ticks: simulation.ticks(),
+            hole_size: simulation.hole_size(),
+            capped: simulation.capped(),
+        }
+    }
+
+    /// Combines the episodes of a finished generation into the scores of the birds
+    fn end_generation(&mut self, episodes: Vec<Episode>) -> Vec<f64> {
+        let all_scores: Vec<Vec<f64>> = episodes.iter().map(|e| e.scores.clone()).collect();
+        let scores = self.params.aggregation().combine(&all_scores);
+        let stats = GenerationStats::new(
+            self.generation,
+            &scores,
+            self.species_count,
+            episodes.iter().map(|e| e.pipes_passed).sum::<f64>() / episodes.len() as f64,
+            episodes.iter().map(|e| e.ticks).max().unwrap_or(0),
+            episodes[0].hole_size,
+            episodes.iter().any(|e| e.capped),
+        );
+        if let Some(callback) = &mut self.on_generation {
+            callback(&stats);
+        }
+        self.curriculum.advance(&stats);
+        self.statistics.record(stats);
+        self.networks = None;
+        self.generation += 1;
+        scores
+    }
+
+    /// Runs a generation without rendering anything, as fast as possible
+    fn run_headless(&mut self) -> Vec<f64> {
+        let mut episodes = Vec::new();
+        for episode in 0..self.params.episodes as usize {
+            let mut simulation = self.new_simulation(episode);
+            simulation.run();
+            episodes.push(self.end_episode(&simulation));
+        }
+        self.end_generation(episodes)
+    }
+
+    /// Runs a generation on the canvas, one episode after the other
+    async fn run_rendered(&mut self) -> Vec<f64> {
+        let mut episodes = Vec::new();
+        for episode in 0..self.params.episodes as usize {
+            let simulation = self.new_simulation(episode);
+            let game = Game::run_game(
+                simulation,
+                self.params.render_count,
+                self.species_count,
+                self.generation,
+                self.stop.clone(),
+            )
+            .await;
+            let lock = &*game.lock().unwrap();
+            episodes.push(self.end_episode(lock.simulation()));
+        }
+        self.end_generation(episodes)
     }
 }
 
 impl neat_gru::game::Game<f64> for TrainingSimulation {
     fn run_generation(&mut self) -> Vec<f64> {
-        Vec::new()
+        self.run_headless()
     }
 
     fn reset_players(&mut self, nets: Vec<NeuralNetwork<f64>>) {
         self.networks = Some(nets);
     }
 
-    fn post_training(&mut self, _: &[Topology<f64>]) {}
+    fn post_training(&mut self, history: &[Topology<f64>]) {
+        self.champions = Some(Champions::new(
+            self.params.mode(),
+            self.params.sensors(),
+            history,
+            CHAMPIONS_COUNT,
+        ));
+    }
 }
 
 #[async_trait]
 impl GameAsync<f64> for TrainingSimulation {
     async fn run_generation_async(&mut self) -> Vec<f64> {
-        let generation = self.generation;
-        self.generation += 1;
-        let width = self.width;
-        let height = self.height;
-        let species_count = self.species_count;
-        let render_count = self.params.render_count;
-        let hole_size = self.params.hole_size;
-        let networks = self.networks.take().unwrap();
-        match self.params.game_type {
-            0 => {
-                let game = Game::<0>::run_game(
-                    width,
-                    height,
-                    render_count,
-                    species_count,
-                    generation,
-                    hole_size,
-                    networks,
-                )
-                .await;
-                let lock = &*game.lock().unwrap();
-                lock.scores.clone()
-            }
-            1 => {
-                let game = Game::<1>::run_game(
-                    width,
-                    height,
-                    render_count,
-                    species_count,
-                    generation,
-                    hole_size,
-                    networks,
-                )
-                .await;
-                let lock = &*game.lock().unwrap();
-                lock.scores.clone()
-            }
-            _ => {
-                panic!("Invalid game type")
-            }
-        }
+        self.run_rendered().await
     }
 }
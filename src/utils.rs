@@ -21,3 +21,66 @@ pub fn request_animation_frame(f: &Closure<dyn FnMut()>) {
         .request_animation_frame(f.as_ref().unchecked_ref())
         .expect("should register `requestAnimationFrame` OK");
 }
+
+/// Makes the browser download `contents` as a file
+pub fn download(filename: &str, contents: &str, mime_type: &str) {
+    let parts = js_sys::Array::of1(&JsValue::from_str(contents));
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_(mime_type);
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).unwrap();
+    let url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
+
+    let document = window().document().unwrap();
+    let anchor = document
+        .create_element("a")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .unwrap();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).unwrap();
+}
+
+/// Sizes the canvas of the given id for a world of `width` by `height` and returns a context
+/// drawing in world units.
+///
+/// The canvas keeps its displayed width and takes the aspect ratio of the world, its backing
+/// store follows `devicePixelRatio` so that the drawing stays sharp on HiDPI screens
+pub fn scaled_context(id: &str, width: f64, height: f64) -> web_sys::CanvasRenderingContext2d {
+    let window = window();
+    let canvas = window
+        .document()
+        .unwrap()
+        .get_element_by_id(id)
+        .unwrap()
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .unwrap();
+    let display_width = match canvas.client_width() {
+        0 => width,
+        client_width => client_width as f64,
+    };
+    let display_height = display_width * height / width;
+    let style = canvas.style();
+    style
+        .set_property("width", &format!("{}px", display_width))
+        .unwrap();
+    style
+        .set_property("height", &format!("{}px", display_height))
+        .unwrap();
+
+    let pixel_ratio = window.device_pixel_ratio();
+    canvas.set_width((display_width * pixel_ratio).round() as u32);
+    canvas.set_height((display_height * pixel_ratio).round() as u32);
+    let context = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()
+        .unwrap();
+    let scale = canvas.width() as f64 / width;
+    context
+        .set_transform(scale, 0.0, 0.0, scale, 0.0, 0.0)
+        .unwrap();
+    context
+}